@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, Sender};
@@ -7,6 +6,7 @@ use std::sync::Arc;
 use std::thread;
 
 use clap::Parser;
+use code_stats::{CodeStats, Counter, Key, Language, Options, Place};
 
 #[derive(Debug, Parser)]
 struct Cli {
@@ -14,28 +14,18 @@ struct Cli {
     types: Vec<String>,
     #[clap(short = 'f')]
     dirs: Vec<PathBuf>,
+    #[clap(flatten)]
+    options: Options,
 }
 
-#[derive(Debug, Clone, Default)]
-struct CodeStats {
-    files: usize,
-    blanks: usize,
-    codes: usize,
-}
-
-impl std::ops::AddAssign for CodeStats {
-    fn add_assign(&mut self, rhs: Self) {
-        self.files += rhs.files;
-        self.blanks += rhs.blanks;
-        self.codes += rhs.codes;
-    }
-}
-
-// type StatKey = (PathBuf, String);
-
-fn main() {
+fn main() -> anyhow::Result<()> {
     let args = Cli::parse();
-    let types = Arc::new(args.types.clone());
+    if let Some(config) = &args.options.languages {
+        Language::load(config)?;
+    }
+    let types = Arc::new(Language::select(&args.types)?);
+    let roots: Vec<&Path> = args.dirs.iter().map(PathBuf::as_path).collect();
+    let counter = Arc::new(Counter::new(args.options, &roots));
     let timer = std::time::Instant::now();
 
     let (sender, receiver) = mpsc::channel();
@@ -44,7 +34,10 @@ fn main() {
     for dir in args.dirs {
         let sender_ = sender.clone();
         let types_ = types.clone();
-        thread_handles.push(thread::spawn(move || find_files(&dir, types_, sender_)));
+        let counter_ = counter.clone();
+        thread_handles.push(thread::spawn(move || {
+            find_files(&dir, Place::default(), types_, counter_, sender_)
+        }));
     }
     drop(sender);
     for handle in thread_handles {
@@ -52,32 +45,32 @@ fn main() {
     }
 
     let mut stats_tot = HashMap::new();
-    for (type_, stats) in receiver {
-        let s = stats_tot.entry(type_).or_insert_with(CodeStats::default);
+    for (key, stats) in receiver {
+        let s = stats_tot.entry(key).or_insert_with(CodeStats::default);
         *s += stats;
     }
     println!("{:?}", stats_tot);
+    if let Some(duplicates) = counter.duplicates() {
+        println!("{:?}", duplicates);
+    }
+    if let Some(markers) = counter.markers() {
+        println!("{:?}", markers);
+    }
     println!("Total used time: {:?}", timer.elapsed());
+    Ok(())
 }
 
-fn count_lines(file_path: &Path, ext: String, sender: Sender<(String, CodeStats)>) {
-    let mut codes = 0;
-    let mut blanks = 0;
-    let buf = std::fs::read_to_string(file_path);
+fn count_lines(
+    file_path: &Path,
+    lang: &Language,
+    key: Key,
+    counter: &Counter,
+    sender: Sender<(Key, CodeStats)>,
+) {
+    let buf = std::fs::read(file_path);
     if let Ok(content) = buf {
-        content.lines().for_each(|line| {
-            if line.trim().is_empty() {
-                blanks += 1;
-            } else {
-                codes += 1;
-            }
-        });
-        let stats = CodeStats {
-            files: 1,
-            blanks,
-            codes,
-        };
-        if sender.send((ext, stats)).is_err() {
+        let stats = counter.count(file_path, &content, lang);
+        if sender.send((key, stats)).is_err() {
             println!("Failed to send codestats of file: {:?}", file_path);
         }
     }
@@ -85,19 +78,26 @@ fn count_lines(file_path: &Path, ext: String, sender: Sender<(String, CodeStats)
 
 fn find_files(
     dir: &Path,
-    types: Arc<Vec<String>>,
-    sender: Sender<(String, CodeStats)>,
+    place: Place,
+    types: Arc<Vec<&'static Language>>,
+    counter: Arc<Counter>,
+    sender: Sender<(Key, CodeStats)>,
 ) -> std::io::Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
+        if counter.is_ignored(&path, path.is_dir()) {
+            continue;
+        }
         if path.is_dir() && !path.is_symlink() {
-            find_files(&path, types.clone(), sender.clone())?;
+            if let Some(place) = counter.enter(&place, &path) {
+                find_files(&path, place, types.clone(), counter.clone(), sender.clone())?;
+            }
         } else {
-            let ext = path.extension().and_then(OsStr::to_str);
-            if let Some(ext) = ext {
-                if types.iter().any(|t| t == ext) {
-                    count_lines(&path, ext.to_owned(), sender.clone());
+            if let Some(lang) = Language::detect(&path) {
+                if types.contains(&lang) {
+                    let key = counter.key(&place, lang);
+                    count_lines(&path, lang, key, &counter, sender.clone());
                 }
             }
         }
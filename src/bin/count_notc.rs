@@ -1,5 +1,9 @@
 use code_stats::Cli;
 use code_stats::CodeStats;
+use code_stats::Counter;
+use code_stats::Key;
+use code_stats::Language;
+use code_stats::Place;
 
 use anyhow::Result;
 use clap::Parser;
@@ -10,83 +14,97 @@ use std::time::Instant;
 use tokio::sync::mpsc::{self, Sender};
 
 #[tokio::main(worker_threads = 2)]
-async fn main() {
+async fn main() -> Result<()> {
     let args = Cli::parse();
+    if let Some(config) = &args.options.languages {
+        Language::load(config)?;
+    }
     let dir = Path::new(&args.dir);
-    let types = Arc::new(args.types);
+    let types = Arc::new(Language::select(&args.types)?);
+    let counter = Arc::new(Counter::new(args.options, &[dir]));
     let (tx, mut rx) = mpsc::channel(1000000);
 
     let timer = Instant::now();
     let res = tokio::spawn(async move {
-        let mut res: HashMap<String, CodeStats> = HashMap::new();
-        while let Some((ext, stats)) = rx.recv().await {
-            let ent = res.entry(ext).or_default();
+        let mut res: HashMap<Key, CodeStats> = HashMap::new();
+        while let Some((key, stats)) = rx.recv().await {
+            let ent = res.entry(key).or_default();
             *ent += stats;
         }
         res
     });
 
-    count_dir(dir, types, tx).await.unwrap();
+    count_dir(dir, Place::default(), types, counter.clone(), tx)
+        .await
+        .unwrap();
 
     match res.await {
         Ok(stats) => println!("{:?}", stats),
         Err(_) => println!("something wrong"),
     }
+    if let Some(duplicates) = counter.duplicates() {
+        println!("{:?}", duplicates);
+    }
+    if let Some(markers) = counter.markers() {
+        println!("{:?}", markers);
+    }
 
     println!("Total time used: {:?}", timer.elapsed());
+    Ok(())
 }
 
 #[async_recursion::async_recursion]
 async fn count_dir(
     dir: &Path,
-    valid_types: Arc<Vec<String>>,
-    sender: Sender<(String, CodeStats)>,
+    place: Place,
+    valid_types: Arc<Vec<&'static Language>>,
+    counter: Arc<Counter>,
+    sender: Sender<(Key, CodeStats)>,
 ) -> Result<()> {
     let paths: Vec<_> = std::fs::read_dir(dir)?
         .filter_map(|en| en.ok())
         .map(|en| en.path())
+        .filter(|p| !counter.is_ignored(p, p.is_dir()))
         .collect();
     let files = paths.iter().filter(|p| p.is_file());
     let dirs = paths.iter().filter(|p| p.is_dir() && !p.is_symlink());
 
     for f in files {
-        if let Some(ext) = f.extension().and_then(std::ffi::OsStr::to_str) {
-            if valid_types.iter().any(|t| t == ext) {
+        if let Some(lang) = Language::detect(f) {
+            if valid_types.contains(&lang) {
                 let path = f.clone();
-                let ext = ext.to_owned();
+                let key = counter.key(&place, lang);
+                let counter_ = counter.clone();
                 let sender_ = sender.clone();
-                tokio::spawn(async move { count_file(path, ext, sender_).await });
+                tokio::spawn(async move { count_file(path, lang, key, counter_, sender_).await });
             }
         }
     }
 
     for d in dirs {
+        let place = match counter.enter(&place, d) {
+            Some(place) => place,
+            None => continue,
+        };
         let vtypes = valid_types.clone();
+        let counter_ = counter.clone();
         let sender_ = sender.clone();
         let dir_ = d.clone();
-        tokio::spawn(async move { count_dir(&dir_, vtypes, sender_).await });
+        tokio::spawn(async move { count_dir(&dir_, place, vtypes, counter_, sender_).await });
     }
 
     Ok(())
 }
 
-async fn count_file(path: PathBuf, ext: String, sender: Sender<(String, CodeStats)>) {
-    let mut codes = 0;
-    let mut blanks = 0;
-
-    if let Ok(buf) = std::fs::read_to_string(path) {
-        buf.lines().for_each(|line| {
-            if line.trim().is_empty() {
-                blanks += 1;
-            } else {
-                codes += 1;
-            }
-        });
-        let res = CodeStats {
-            files: 1,
-            blanks,
-            codes,
-        };
-        let _ = sender.send((ext, res)).await;
+async fn count_file(
+    path: PathBuf,
+    lang: &Language,
+    key: Key,
+    counter: Arc<Counter>,
+    sender: Sender<(Key, CodeStats)>,
+) {
+    if let Ok(buf) = std::fs::read(&path) {
+        let res = counter.count(&path, &buf, lang);
+        let _ = sender.send((key, res)).await;
     }
 }
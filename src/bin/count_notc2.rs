@@ -1,48 +1,71 @@
 use code_stats::Cli;
 use code_stats::CodeStats;
+use code_stats::Counter;
+use code_stats::Key;
+use code_stats::Language;
+use code_stats::Place;
 
 use clap::Parser;
 use std::collections::HashMap;
-use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     let args = Cli::parse();
+    if let Some(config) = &args.options.languages {
+        Language::load(config)?;
+    }
     let dir = args.dir.clone();
-    let types = args.types;
+    let types = Language::select(&args.types)?;
+    let counter = Arc::new(Counter::new(args.options, &[&dir]));
 
     let (tx, rx) = mpsc::channel(100000);
     let timer = Instant::now();
 
     // start the task manager
-    let handle = tokio::spawn(async move { task_manager(rx).await });
+    let counter_ = counter.clone();
+    let handle = tokio::spawn(async move { task_manager(rx, counter_).await });
     // scan the folder recursively
-    tokio::spawn(async move { scan_dir(Path::new(&dir), &types, tx).await });
+    let counter_ = counter.clone();
+    tokio::spawn(async move {
+        scan_dir(Path::new(&dir), Place::default(), &types, &counter_, tx).await
+    });
 
     let res = handle.await.unwrap();
     println!("Code stats Result: {:#?}", res);
+    if let Some(duplicates) = counter.duplicates() {
+        println!("Duplicates: {:#?}", duplicates);
+    }
+    if let Some(markers) = counter.markers() {
+        println!("Markers: {:#?}", markers);
+    }
     println!("Total time used: {:?}", timer.elapsed());
+    Ok(())
 }
 
-async fn task_manager(mut rcvr: Receiver<(PathBuf, String)>) -> HashMap<String, CodeStats> {
+async fn task_manager(
+    mut rcvr: Receiver<(PathBuf, &'static Language, Key)>,
+    counter: Arc<Counter>,
+) -> HashMap<Key, CodeStats> {
     let (tx, mut rx) = mpsc::channel(10000);
 
     let res = tokio::spawn(async move {
-        let mut res: HashMap<String, CodeStats> = HashMap::new();
-        while let Some((ext, stats)) = rx.recv().await {
-            let ent = res.entry(ext).or_default();
+        let mut res: HashMap<Key, CodeStats> = HashMap::new();
+        while let Some((key, stats)) = rx.recv().await {
+            let ent = res.entry(key).or_default();
             *ent += stats;
         }
         res
     });
 
-    while let Some((path, ext)) = rcvr.recv().await {
+    while let Some((path, lang, key)) = rcvr.recv().await {
+        let counter_ = counter.clone();
         let tx_ = tx.clone();
-        tokio::spawn(async move { count_file(path, ext, tx_).await });
+        tokio::spawn(async move { count_file(path, lang, key, counter_, tx_).await });
     }
     drop(tx);
 
@@ -52,19 +75,25 @@ async fn task_manager(mut rcvr: Receiver<(PathBuf, String)>) -> HashMap<String,
 #[async_recursion::async_recursion]
 async fn scan_dir(
     dir: &Path,
-    types: &Vec<String>,
-    sender: Sender<(PathBuf, String)>,
+    place: Place,
+    types: &Vec<&'static Language>,
+    counter: &Counter,
+    sender: Sender<(PathBuf, &'static Language, Key)>,
 ) -> std::io::Result<()> {
     for entry in fs::read_dir(dir)? {
         let path = entry?.path();
+        if counter.is_ignored(&path, path.is_dir()) {
+            continue;
+        }
         if path.is_dir() && !path.is_symlink() {
-            scan_dir(&path, types, sender.clone()).await?;
+            if let Some(place) = counter.enter(&place, &path) {
+                scan_dir(&path, place, types, counter, sender.clone()).await?;
+            }
         } else {
-            let ext = path.extension().and_then(OsStr::to_str);
-            if let Some(ext) = ext {
-                if types.iter().any(|t| t == ext) {
-                    let ext = ext.to_owned();
-                    let _ = sender.send((path, ext)).await;
+            if let Some(lang) = Language::detect(&path) {
+                if types.contains(&lang) {
+                    let key = counter.key(&place, lang);
+                    let _ = sender.send((path, lang, key)).await;
                 }
             }
         }
@@ -72,22 +101,15 @@ async fn scan_dir(
     Ok(())
 }
 
-async fn count_file(path: PathBuf, ext: String, sender: Sender<(String, CodeStats)>) {
-    let mut codes = 0;
-    let mut blanks = 0;
-    if let Ok(buf) = fs::read_to_string(path) {
-        buf.lines().for_each(|line| {
-            if line.trim().is_empty() {
-                blanks += 1;
-            } else {
-                codes += 1;
-            }
-        });
-        let stats = CodeStats {
-            files: 1,
-            codes,
-            blanks,
-        };
-        let _ = sender.send((ext, stats)).await;
+async fn count_file(
+    path: PathBuf,
+    lang: &Language,
+    key: Key,
+    counter: Arc<Counter>,
+    sender: Sender<(Key, CodeStats)>,
+) {
+    if let Ok(buf) = fs::read(&path) {
+        let stats = counter.count(&path, &buf, lang);
+        let _ = sender.send((key, stats)).await;
     }
 }
@@ -1,7 +1,11 @@
+use code_stats::{
+    by_language, print_duplicates, print_groups, print_markers, CodeStats, Counter, Key, Language,
+    Options, Place,
+};
+
 use anyhow::Result;
 use clap::Parser;
 use futures::stream::{FuturesUnordered, StreamExt};
-use glob::glob;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -13,185 +17,148 @@ use tokio::sync::Semaphore;
 #[derive(Debug, Parser)]
 struct Args {
     #[clap(short = 't')]
-    exts: Vec<String>,
+    types: Vec<String>,
     #[clap(short = 'f')]
     path: PathBuf,
+    #[clap(flatten)]
+    options: Options,
 }
 
-#[derive(Debug, Clone, Default)]
-struct CodeStats {
-    files: usize,
-    blanks: usize,
-    codes: usize,
-}
-
-impl CodeStats {
-    fn new() -> Self {
-        Self::default()
-    }
-
-    fn lines(&self) -> usize {
-        self.blanks + self.codes
-    }
-}
-
-impl std::ops::Add for CodeStats {
-    type Output = CodeStats;
-    fn add(self, rhs: Self) -> Self::Output {
-        CodeStats {
-            files: self.files + rhs.files,
-            blanks: self.blanks + rhs.blanks,
-            codes: self.codes + rhs.codes,
-        }
-    }
-}
-
-impl std::ops::AddAssign for CodeStats {
-    fn add_assign(&mut self, rhs: Self) {
-        self.files += rhs.files;
-        self.blanks += rhs.blanks;
-        self.codes += rhs.codes;
-    }
-}
-
-fn count_file(path: &Path) -> Result<CodeStats> {
-    let mut codes = 0;
-    let mut blanks = 0;
-
-    let buf = std::fs::read_to_string(path).unwrap();
-    buf.lines().for_each(|line| {
-        if line.trim().is_empty() {
-            blanks += 1;
-        } else {
-            codes += 1;
-        }
-    });
-
-    Ok(CodeStats {
-        files: 1,
-        blanks,
-        codes,
-    })
+fn count_file(path: &Path, lang: &Language, counter: &Counter) -> Result<CodeStats> {
+    let buf = std::fs::read(path).unwrap();
+    Ok(counter.count(path, &buf, lang))
 }
 
-async fn count_file2(path: &Path) -> Result<CodeStats> {
-    let mut codes = 0;
-    let mut blanks = 0;
-
+#[allow(dead_code)]
+async fn count_file2(path: &Path, lang: &Language, counter: &Counter) -> Result<CodeStats> {
     let mut file = tokio::fs::File::open(path).await?;
-    let mut buf = String::new();
-    file.read_to_string(&mut buf).await?;
-    buf.lines().for_each(|line| {
-        if line.trim().is_empty() {
-            blanks += 1;
-        } else {
-            codes += 1;
-        }
-    });
-
-    Ok(CodeStats {
-        files: 1,
-        blanks,
-        codes,
-    })
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).await?;
+    Ok(counter.count(path, &buf, lang))
 }
 
-async fn count_dir(path: &Path, ext: &str) -> Result<CodeStats> {
-    let paths = glob(&format!("{}**/*.{}", path.to_string_lossy(), ext))?;
-    let paths = paths.filter_map(|p| p.ok());
+async fn count_dir(
+    path: &Path,
+    lang: &'static Language,
+    counter: Arc<Counter>,
+) -> Result<HashMap<Key, CodeStats>> {
+    let root = path;
+    let paths = lang.glob(path, |p| !counter.is_ignored(p, false))?;
 
     let mut futs = FuturesUnordered::new();
-    let mut stats = CodeStats::new();
+    let mut res: HashMap<Key, CodeStats> = HashMap::new();
 
     for path in paths {
-        let fut = tokio::spawn(async move { count_file(&path) });
+        let place = match counter.place(root, &path) {
+            Some(place) => place,
+            None => continue,
+        };
+        let key = counter.key(&place, lang);
+        let counter_ = counter.clone();
+        let fut = tokio::spawn(async move { (key, count_file(&path, lang, &counter_)) });
         futs.push(fut);
 
         if futs.len() == 1000 {
-            if let Some(Ok(Ok(s))) = futs.next().await {
-                stats += s;
+            if let Some(Ok((key, Ok(s)))) = futs.next().await {
+                *res.entry(key).or_default() += s;
             }
         }
     }
 
-    while let Some(Ok(Ok(s))) = futs.next().await {
-        stats += s;
+    while let Some(Ok((key, Ok(s)))) = futs.next().await {
+        *res.entry(key).or_default() += s;
+    }
+    // languages without files still get their row
+    if res.is_empty() {
+        res.insert(counter.key(&Place::default(), lang), CodeStats::new());
     }
-    Ok(stats)
+    Ok(res)
 }
 
-async fn count_dir2(path: &Path, ext: &str) -> Result<CodeStats> {
-    let paths = glob(&format!("{}**/*.{}", path.to_string_lossy(), ext))?;
-    let paths = paths.filter_map(|p| p.ok());
+#[allow(dead_code)]
+async fn count_dir2(
+    path: &Path,
+    lang: &'static Language,
+    counter: Arc<Counter>,
+) -> Result<HashMap<Key, CodeStats>> {
+    let root = path;
+    let paths = lang.glob(path, |p| !counter.is_ignored(p, false))?;
 
     let (tx, mut rx) = mpsc::channel(300);
     let sem = Arc::new(Semaphore::new(1000));
 
     for path in paths {
+        let place = match counter.place(root, &path) {
+            Some(place) => place,
+            None => continue,
+        };
+        let key = counter.key(&place, lang);
         let tx_ = tx.clone();
         let sem_clone = Arc::clone(&sem);
+        let counter_ = counter.clone();
         tokio::spawn(async move {
             let aq = sem_clone.try_acquire();
             if let Ok(_guard) = aq {
-                let count = count_file2(&path).await;
+                let count = count_file2(&path, lang, &counter_).await;
                 if let Ok(s) = count {
-                    tx_.send(s).await.unwrap();
+                    tx_.send((key, s)).await.unwrap();
                 }
             }
         });
     }
     drop(tx);
 
-    let mut res = CodeStats::new();
-    while let Some(stats) = rx.recv().await {
-        res += stats;
+    let mut res: HashMap<Key, CodeStats> = HashMap::new();
+    while let Some((key, stats)) = rx.recv().await {
+        *res.entry(key).or_default() += stats;
     }
-    Ok(res)
-}
-
-fn print_result(res: HashMap<String, CodeStats>) {
-    println!(
-        " {:<6} {:>12} {:>12} {:>12} {:>12}",
-        "Ext.", "Files", "Lines", "Codes", "Blanks"
-    );
-    for (ext, stats) in res.iter() {
-        println!(
-            " {:<6} {:>12} {:>12} {:>12} {:>12}",
-            ext,
-            stats.files,
-            stats.lines(),
-            stats.codes,
-            stats.blanks
-        );
+    // languages without files still get their row
+    if res.is_empty() {
+        res.insert(counter.key(&Place::default(), lang), CodeStats::new());
     }
+    Ok(res)
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<()> {
     let args = Args::parse();
-    let exts: Vec<String> = args.exts;
+    if let Some(config) = &args.options.languages {
+        Language::load(config)?;
+    }
+    let langs = Language::select(&args.types)?;
     let path: PathBuf = args.path;
+    let counter = Arc::new(Counter::new(args.options, &[&path]));
 
     let timer = Instant::now();
     let mut res = HashMap::new();
     let (tx, mut rx) = mpsc::channel(100);
 
-    for ext in exts {
+    for lang in langs {
         let tx_ = tx.clone();
         let p_ = path.clone();
+        let counter_ = counter.clone();
         tokio::spawn(async move {
-            let stats = count_dir(&p_, &ext).await;
+            let stats = count_dir(&p_, lang, counter_).await;
             if let Ok(s) = stats {
-                tx_.send((ext, s)).await.unwrap();
+                tx_.send(s).await.unwrap();
             }
         });
     }
     drop(tx);
 
-    while let Some((ext, stats)) = rx.recv().await {
-        res.insert(ext, stats);
+    while let Some(stats) = rx.recv().await {
+        res.extend(stats);
     }
 
-    print_result(res);
+    print_groups(&res);
+    if let Some(duplicates) = counter.duplicates() {
+        println!();
+        print_duplicates(&duplicates, &by_language(&res));
+    }
+    if let Some(markers) = counter.markers() {
+        println!();
+        print_markers(&markers);
+    }
     println!("Total time elapsed: {:?}", timer.elapsed());
+    Ok(())
 }
@@ -1,3 +1,4 @@
+use crate::counter::Options;
 use clap::Parser;
 use std::path::PathBuf;
 
@@ -7,4 +8,6 @@ pub struct Cli {
     pub types: Vec<String>,
     #[clap(short = 'f')]
     pub dir: PathBuf,
+    #[clap(flatten)]
+    pub options: Options,
 }
@@ -1,14 +1,289 @@
-#[derive(Debug, Clone, Default)]
+use crate::counter::Options;
+use crate::embedded;
+use crate::language::Language;
+use crate::lines::{self, LineEnding};
+use crate::markers::Marker;
+use crate::syntax::{LineClassifier, LineKind, Syntax};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct CodeStats {
     pub files: usize,
     pub blanks: usize,
+    pub comments: usize,
+    pub docs: usize,
     pub codes: usize,
+    /// Code lines of only braces, brackets or `end`, kept out of the codes when asked to
+    pub structural: usize,
+    /// The part of the stats above that is test code, by test files or test items like Rust's
+    /// `#[cfg(test)]` modules
+    pub test_files: usize,
+    pub test_lines: usize,
+    pub test_codes: usize,
+    /// Size of the files as read from disk
+    pub bytes: usize,
+    /// Characters of all lines, line breaks left out
+    pub chars: usize,
+    pub max_line_length: usize,
+    /// Lines longer than the configured width
+    pub long_lines: usize,
+    /// Logical lines, the statements as each language terminates them
+    pub logical: usize,
+    /// Files that were decoded from UTF-16 or the byte-level fallback
+    pub non_utf8: usize,
+    /// Files skipped because their content is binary
+    pub binaries: usize,
+    /// Hard links and byte-identical copies of files counted before, skipped in dedup mode
+    pub duplicate_files: usize,
+    /// Files by their line breaks, files without any line break are in none of these
+    pub lf: usize,
+    pub crlf: usize,
+    pub cr: usize,
+    pub mixed: usize,
+    /// Generated files and their lines, kept out of the hand-written stats above
+    pub generated_files: usize,
+    pub generated_lines: usize,
+    /// Third-party files and their lines, kept out the same way
+    pub vendored_files: usize,
+    pub vendored_lines: usize,
+    /// Minified bundles and their lines, kept out the same way
+    pub minified_files: usize,
+    pub minified_lines: usize,
+    /// Estimated cyclomatic complexity, the sum over all files and the most complex file
+    pub complexity: usize,
+    pub max_complexity: usize,
+    /// Marker comments like `TODO` and `FIXME`, keyed by marker
+    pub markers: BTreeMap<String, usize>,
+    /// Lines of the files by the license of their header, see `NO_LICENSE` for files without one
+    pub licenses: BTreeMap<String, usize>,
+    /// Stats of other languages embedded in these files, keyed by language name
+    pub children: BTreeMap<String, CodeStats>,
+}
+
+impl CodeStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the lines of a single file's content
+    pub fn count(content: &str, lang: &Language, options: &Options) -> Self {
+        CodeStats::count_with_markers(content, lang, options, &mut vec![])
+    }
+
+    /// Count the lines of a single file's content, and collect its marker comments into `found`
+    /// when they are listed
+    pub(crate) fn count_with_markers(
+        content: &str,
+        lang: &Language,
+        options: &Options,
+        found: &mut Vec<Marker>,
+    ) -> Self {
+        let mut stats = CodeStats::count_content(content, lang, options, found);
+        stats.files = 1;
+        stats.max_complexity = stats.summarise().complexity;
+        match LineEnding::detect(content) {
+            LineEnding::None => {}
+            LineEnding::Lf => stats.lf = 1,
+            LineEnding::CrLf => stats.crlf = 1,
+            LineEnding::Cr => stats.cr = 1,
+            LineEnding::Mixed => stats.mixed = 1,
+        }
+        stats
+    }
+
+    /// Count the lines of some content without any file level stats
+    pub(crate) fn count_content(
+        content: &str,
+        lang: &Language,
+        options: &Options,
+        found: &mut Vec<Marker>,
+    ) -> Self {
+        match lang.embedding {
+            Some(embedding) => embedded::count(content, lang, embedding, options, found),
+            None => CodeStats::count_lines(lines::lines(content), lang.syntax, options, found),
+        }
+    }
+
+    pub(crate) fn count_lines<'a>(
+        lines: impl Iterator<Item = &'a str>,
+        syntax: &'static Syntax,
+        options: &Options,
+        found: &mut Vec<Marker>,
+    ) -> Self {
+        let mut classifier = LineClassifier::new(syntax);
+        let mut stats = CodeStats::new();
+        for (i, line) in lines.enumerate() {
+            stats.count_line(&mut classifier, i + 1, line, options, found);
+        }
+        stats
+    }
+
+    /// Classify line `number` of a file, measure it, count the statements and branches of its
+    /// code and the markers in its comments, which go to `found` too when they are listed
+    pub(crate) fn count_line(
+        &mut self,
+        classifier: &mut LineClassifier,
+        number: usize,
+        line: &str,
+        options: &Options,
+        found: &mut Vec<Marker>,
+    ) -> LineKind {
+        let kind = classifier.classify(line);
+        let structural = kind == LineKind::Code && options.structural && classifier.is_structural();
+        if structural {
+            self.structural += 1;
+        } else {
+            self.add_line(kind);
+        }
+        if classifier.in_test() {
+            self.test_lines += 1;
+            if kind == LineKind::Code && !structural {
+                self.test_codes += 1;
+            }
+        }
+        let length = line.chars().count();
+        self.chars += length;
+        self.max_line_length = self.max_line_length.max(length);
+        if length > options.width {
+            self.long_lines += 1;
+        }
+        self.logical += classifier.statements();
+        self.complexity += classifier.branches();
+        for marker in &options.markers {
+            let mentions = classifier.mentions(marker);
+            if mentions > 0 {
+                *self.markers.entry(marker.clone()).or_default() += mentions;
+                if options.list_markers {
+                    found.push(Marker {
+                        path: Default::default(),
+                        line: number,
+                        marker: marker.clone(),
+                        text: line.trim().to_owned(),
+                    });
+                }
+            }
+        }
+        kind
+    }
+
+    pub fn add_line(&mut self, kind: LineKind) {
+        match kind {
+            LineKind::Blank => self.blanks += 1,
+            LineKind::Comment => self.comments += 1,
+            LineKind::Doc => self.docs += 1,
+            LineKind::Code => self.codes += 1,
+        }
+    }
+
+    /// Mark all the lines, embedded ones included, as test code
+    pub(crate) fn mark_tests(&mut self) {
+        self.test_files = self.files;
+        self.test_lines = self.lines();
+        self.test_codes = self.codes;
+        self.children.values_mut().for_each(CodeStats::mark_tests);
+    }
+
+    pub(crate) fn child(&mut self, lang: &str) -> &mut CodeStats {
+        self.children.entry(lang.to_owned()).or_default()
+    }
+
+    pub fn lines(&self) -> usize {
+        self.blanks + self.comments + self.docs + self.codes + self.structural
+    }
+
+    /// The average length of a line in characters
+    pub fn mean_line_length(&self) -> f64 {
+        match self.lines() {
+            0 => 0.0,
+            lines => self.chars as f64 / lines as f64,
+        }
+    }
+
+    /// The average complexity of a file
+    pub fn mean_complexity(&self) -> f64 {
+        match self.files {
+            0 => 0.0,
+            files => self.complexity as f64 / files as f64,
+        }
+    }
+
+    /// The own counts plus the counts of all embedded children
+    pub fn summarise(&self) -> CodeStats {
+        let mut total = CodeStats {
+            children: BTreeMap::new(),
+            ..self.clone()
+        };
+        for child in self.children.values() {
+            total += child.summarise();
+        }
+        total
+    }
+}
+
+impl std::ops::Add for CodeStats {
+    type Output = CodeStats;
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
 }
 
 impl std::ops::AddAssign for CodeStats {
     fn add_assign(&mut self, rhs: Self) {
         self.files += rhs.files;
         self.blanks += rhs.blanks;
+        self.comments += rhs.comments;
+        self.docs += rhs.docs;
         self.codes += rhs.codes;
+        self.structural += rhs.structural;
+        self.test_files += rhs.test_files;
+        self.test_lines += rhs.test_lines;
+        self.test_codes += rhs.test_codes;
+        self.logical += rhs.logical;
+        self.bytes += rhs.bytes;
+        self.chars += rhs.chars;
+        self.max_line_length = self.max_line_length.max(rhs.max_line_length);
+        self.long_lines += rhs.long_lines;
+        self.non_utf8 += rhs.non_utf8;
+        self.binaries += rhs.binaries;
+        self.duplicate_files += rhs.duplicate_files;
+        self.lf += rhs.lf;
+        self.crlf += rhs.crlf;
+        self.cr += rhs.cr;
+        self.mixed += rhs.mixed;
+        self.generated_files += rhs.generated_files;
+        self.generated_lines += rhs.generated_lines;
+        self.vendored_files += rhs.vendored_files;
+        self.vendored_lines += rhs.vendored_lines;
+        self.minified_files += rhs.minified_files;
+        self.minified_lines += rhs.minified_lines;
+        self.complexity += rhs.complexity;
+        self.max_complexity = self.max_complexity.max(rhs.max_complexity);
+        for (marker, count) in rhs.markers {
+            *self.markers.entry(marker).or_default() += count;
+        }
+        for (license, lines) in rhs.licenses {
+            *self.licenses.entry(license).or_default() += lines;
+        }
+        for (lang, stats) in rhs.children {
+            *self.child(&lang) += stats;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_shows_the_test_half() {
+        let rust = Language::from_name("rust").unwrap();
+        let content = "fn main() {}\n#[cfg(test)]\nmod tests {\n    fn t() {}\n}\n";
+        let stats = CodeStats::count(content, rust, &Options::default());
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["codes"], 5);
+        assert_eq!(json["test_codes"], 4);
+        assert_eq!(json["test_lines"], 4);
     }
 }
@@ -0,0 +1,269 @@
+use crate::codestats::CodeStats;
+use crate::dedup::Dedup;
+use crate::duplicates::{DuplicateFinder, Duplicates};
+use crate::encoding::{self, Encoding};
+use crate::filter::Filter;
+use crate::generated::Generated;
+use crate::gitignore::GitIgnore;
+use crate::language::Language;
+use crate::license::{self, NO_LICENSE};
+use crate::markers::{Marker, MarkerFinder};
+use crate::place::{Key, Place};
+use crate::testcode;
+use crate::vendored::Vendored;
+use clap::Args;
+use glob::Pattern;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+/// Counting options shared by every strategy
+#[derive(Debug, Clone, Args)]
+pub struct Options {
+    /// TOML file declaring extra languages and their comment rules
+    #[clap(long, value_name = "FILE")]
+    pub languages: Option<PathBuf>,
+    /// Extra header marker of generated files, besides `@generated`, `DO NOT EDIT` and the like
+    #[clap(long = "generated-marker")]
+    pub generated_markers: Vec<String>,
+    /// Extra path pattern of generated files, besides `*.pb.cc`, `*_pb2.py` and the like
+    #[clap(long = "generated-path")]
+    pub generated_paths: Vec<Pattern>,
+    /// Extra path pattern of vendored files, besides `third_party/`, `node_modules/` and the like
+    #[clap(long = "vendored-path")]
+    pub vendored_paths: Vec<Pattern>,
+    /// Leave vendored and minified files out instead of reporting them apart
+    #[clap(long)]
+    pub exclude_vendored: bool,
+    /// Lines longer than this many characters are counted as long lines
+    #[clap(long, default_value_t = 100)]
+    pub width: usize,
+    /// Count lines of only braces, brackets or `end` as structural instead of code
+    #[clap(long)]
+    pub structural: bool,
+    /// Find blocks of this many code lines that are duplicated across the counted files
+    #[clap(long, value_name = "LINES")]
+    pub duplicates: Option<usize>,
+    /// Count hard links and byte-identical copies of a file only once
+    #[clap(long)]
+    pub dedup: bool,
+    /// Marker counted in comments
+    #[clap(long = "marker", default_values = &MARKERS)]
+    pub markers: Vec<String>,
+    /// List every marker comment with its file and line
+    #[clap(long)]
+    pub list_markers: bool,
+    /// Walk into everything, ignoring `.gitignore`, `.ignore` and git excludes
+    #[clap(long)]
+    pub no_ignore: bool,
+    /// Skip files and directories matching a glob, e.g. `prebuilts/**`
+    #[clap(long = "exclude", value_name = "GLOB")]
+    pub excludes: Vec<Pattern>,
+    /// Only count files matching one of these globs, e.g. `*.rs` or `src/**`
+    #[clap(long = "include", value_name = "GLOB")]
+    pub includes: Vec<Pattern>,
+    /// Only count files at most this many directories below the root, `1` for the root's own files
+    #[clap(long, value_name = "N")]
+    pub max_depth: Option<NonZeroUsize>,
+    /// Aggregate stats per directory this many levels below the root, e.g. `frameworks/base`
+    #[clap(long, value_name = "N")]
+    pub group_depth: Option<usize>,
+}
+
+/// Markers of technical debt counted by default
+const MARKERS: [&str; 4] = ["TODO", "FIXME", "XXX", "HACK"];
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            languages: None,
+            generated_markers: vec![],
+            generated_paths: vec![],
+            vendored_paths: vec![],
+            exclude_vendored: false,
+            width: 100,
+            structural: false,
+            duplicates: None,
+            dedup: false,
+            markers: MARKERS.iter().map(|m| m.to_string()).collect(),
+            list_markers: false,
+            no_ignore: false,
+            excludes: vec![],
+            includes: vec![],
+            max_depth: None,
+            group_depth: None,
+        }
+    }
+}
+
+/// The counting pipeline every strategy hands its files to, from raw bytes to stats
+#[derive(Debug)]
+pub struct Counter {
+    generated: Generated,
+    vendored: Vendored,
+    exclude_vendored: bool,
+    duplicates: Option<DuplicateFinder>,
+    dedup: Option<Dedup>,
+    markers: Option<MarkerFinder>,
+    gitignore: Option<GitIgnore>,
+    filter: Filter,
+    /// Directories the walk starts from, paths are matched below them
+    roots: Vec<PathBuf>,
+    options: Options,
+}
+
+impl Counter {
+    pub fn new(options: Options, roots: &[&Path]) -> Self {
+        let roots: Vec<PathBuf> = roots.iter().map(|root| root.to_path_buf()).collect();
+        Counter {
+            generated: Generated::new(&options),
+            vendored: Vendored::new(&options),
+            exclude_vendored: options.exclude_vendored,
+            duplicates: options.duplicates.map(DuplicateFinder::new),
+            dedup: options.dedup.then(Dedup::default),
+            markers: options.list_markers.then(MarkerFinder::default),
+            gitignore: (!options.no_ignore).then(|| GitIgnore::new(&roots)),
+            filter: Filter::new(&options),
+            roots,
+            options,
+        }
+    }
+
+    /// Whether the walk should leave out a file or directory, by the default skip list, the
+    /// exclude and include patterns and the ignore files
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.filter.is_skipped(self.relative(path), is_dir)
+            || self
+                .gitignore
+                .as_ref()
+                .is_some_and(|gitignore| gitignore.is_ignored(path, self.root(path), is_dir))
+    }
+
+    /// The place of a directory met at `place`, or `None` if its files are beyond the maximum
+    /// depth and it isn't walked into
+    pub fn enter(&self, place: &Place, dir: &Path) -> Option<Place> {
+        if self
+            .options
+            .max_depth
+            .is_some_and(|max| place.depth + 1 >= max.get())
+        {
+            return None;
+        }
+        let name = dir.file_name()?.to_string_lossy();
+        Some(place.enter(&name, self.options.group_depth))
+    }
+
+    /// The place of the directory of a file that a glob found below `root`, or `None` if the
+    /// file is beyond the maximum depth
+    pub fn place(&self, root: &Path, path: &Path) -> Option<Place> {
+        let place = Place::of(root, path, self.options.group_depth);
+        match self.options.max_depth {
+            Some(max) if place.depth >= max.get() => None,
+            _ => Some(place),
+        }
+    }
+
+    /// The deepest root a path is in
+    fn root(&self, path: &Path) -> Option<&Path> {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .map(PathBuf::as_path)
+    }
+
+    /// A path below the deepest root it is in, or the path itself if it is in none
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        self.root(path)
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+    }
+
+    /// What a file counted at `place` is aggregated under
+    pub fn key(&self, place: &Place, lang: &Language) -> Key {
+        Key::new(place, lang.name)
+    }
+
+    /// Count a single file whatever its encoding. Binary files and copies of files counted before
+    /// are only recorded as skipped, and vendored, minified and generated files are kept apart
+    /// from the hand-written stats.
+    pub fn count(&self, path: &Path, bytes: &[u8], lang: &Language) -> CodeStats {
+        let vendored = self.vendored.is_vendored(path, self.relative(path));
+        if vendored && self.exclude_vendored {
+            return CodeStats::new();
+        }
+        if let Some(dedup) = &self.dedup {
+            if dedup.is_duplicate(path, lang.name, bytes) {
+                return CodeStats {
+                    duplicate_files: 1,
+                    ..Default::default()
+                };
+            }
+        }
+        if encoding::is_binary(bytes) {
+            return CodeStats {
+                binaries: 1,
+                ..Default::default()
+            };
+        }
+        let (content, encoding) = encoding::decode(bytes);
+        let mut marker_lines = vec![];
+        let mut stats =
+            CodeStats::count_with_markers(&content, lang, &self.options, &mut marker_lines);
+        stats.bytes = bytes.len();
+        if encoding != Encoding::Utf8 {
+            stats.non_utf8 = 1;
+        }
+        if vendored {
+            stats = CodeStats {
+                vendored_files: stats.files,
+                vendored_lines: stats.summarise().lines(),
+                ..Default::default()
+            };
+        } else if self.vendored.is_minified(path, &content) {
+            if self.exclude_vendored {
+                return CodeStats::new();
+            }
+            stats = CodeStats {
+                minified_files: stats.files,
+                minified_lines: stats.summarise().lines(),
+                ..Default::default()
+            };
+        } else if self.generated.is_generated(self.relative(path), &content) {
+            stats = CodeStats {
+                generated_files: stats.files,
+                generated_lines: stats.summarise().lines(),
+                ..Default::default()
+            };
+        } else {
+            if testcode::is_test_path(self.relative(path)) {
+                stats.mark_tests();
+            }
+            let license = license::detect(&content, lang.syntax);
+            let license = license.unwrap_or_else(|| NO_LICENSE.to_owned());
+            stats.licenses.insert(license, stats.summarise().lines());
+            if let Some(duplicates) = &self.duplicates {
+                duplicates.add(path, lang.name, lang.syntax, &content);
+            }
+            if let Some(markers) = &self.markers {
+                markers.add(path, marker_lines);
+            }
+        }
+        stats
+    }
+
+    /// Match the code of every file counted so far, if duplicate detection is on
+    pub fn duplicates(&self) -> Option<Duplicates> {
+        self.duplicates.as_ref().map(DuplicateFinder::finish)
+    }
+
+    /// The marker comments of every file counted so far, if they are listed
+    pub fn markers(&self) -> Option<Vec<Marker>> {
+        self.markers.as_ref().map(MarkerFinder::finish)
+    }
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Counter::new(Options::default(), &[])
+    }
+}
@@ -0,0 +1,108 @@
+use crate::language::Language;
+use crate::syntax::{Syntax, PLAIN};
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The languages of a config file, keyed by name
+///
+/// ```toml
+/// [languages.HIDL]
+/// extensions = ["hal"]
+/// line_comments = ["//"]
+/// block_comments = [["/*", "*/"]]
+/// quotes = [["\"", "\""]]
+/// ```
+#[derive(Debug, Deserialize)]
+struct Config {
+    languages: BTreeMap<String, LanguageConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LanguageConfig {
+    extensions: Vec<String>,
+    filenames: Vec<String>,
+    aliases: Vec<String>,
+    line_comments: Vec<String>,
+    block_comments: Vec<(String, String)>,
+    nested: bool,
+    quotes: Vec<(String, String)>,
+    verbatim_quotes: Vec<(String, String)>,
+}
+
+/// Read the languages of a config file. They live as long as the program, like the built-in
+/// ones.
+pub(crate) fn read(path: &Path) -> anyhow::Result<Vec<Language>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("cannot read language config {}", path.display()))?;
+    let config: Config = toml::from_str(&content)
+        .with_context(|| format!("invalid language config {}", path.display()))?;
+    config
+        .languages
+        .into_iter()
+        .map(|(name, lang)| {
+            check_tokens(&lang)
+                .with_context(|| format!("invalid language {} in {}", name, path.display()))?;
+            let syntax = Syntax {
+                line_comments: leak_strs(lang.line_comments),
+                block_comments: leak_pairs(lang.block_comments),
+                nested: lang.nested,
+                quotes: leak_pairs(lang.quotes),
+                verbatim_quotes: leak_pairs(lang.verbatim_quotes),
+                ..PLAIN
+            };
+            // names are looked up in lowercase
+            let extensions = lang.extensions.iter().map(|e| e.to_lowercase()).collect();
+            let aliases = lang.aliases.iter().map(|a| a.to_lowercase()).collect();
+            Ok(Language {
+                name: leak(name),
+                extensions: leak_strs(extensions),
+                filenames: leak_strs(lang.filenames),
+                aliases: leak_strs(aliases),
+                syntax: Box::leak(Box::new(syntax)),
+                embedding: None,
+            })
+        })
+        .collect()
+}
+
+/// Empty comment and quote tokens would match everywhere without moving the classifier forward
+fn check_tokens(lang: &LanguageConfig) -> anyhow::Result<()> {
+    let pairs = lang
+        .block_comments
+        .iter()
+        .chain(&lang.quotes)
+        .chain(&lang.verbatim_quotes)
+        .flat_map(|(start, end)| [start, end]);
+    if lang.line_comments.iter().chain(pairs).any(String::is_empty) {
+        anyhow::bail!("comment and quote tokens cannot be empty");
+    }
+    Ok(())
+}
+
+/// A language for a `-t` type that names no known language, the files with that extension are
+/// counted as plain text
+pub(crate) fn plain(ext: &str) -> Language {
+    Language {
+        name: leak(ext.to_owned()),
+        extensions: leak_strs(vec![ext.to_lowercase()]),
+        filenames: &[],
+        aliases: &[],
+        syntax: &PLAIN,
+        embedding: None,
+    }
+}
+
+fn leak(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
+fn leak_strs(strs: Vec<String>) -> &'static [&'static str] {
+    Box::leak(strs.into_iter().map(leak).collect())
+}
+
+fn leak_pairs(pairs: Vec<(String, String)>) -> &'static [(&'static str, &'static str)] {
+    Box::leak(pairs.into_iter().map(|(a, b)| (leak(a), leak(b))).collect())
+}
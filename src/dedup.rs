@@ -0,0 +1,49 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Remembers the files counted so far, so that hard links and byte-identical copies are only
+/// counted once per language
+#[derive(Debug, Default)]
+pub(crate) struct Dedup {
+    /// Language, device and inode of files with more than one link
+    inodes: Mutex<HashSet<(&'static str, u64, u64)>>,
+    /// Language, hash and length of file contents
+    contents: Mutex<HashSet<(&'static str, u64, usize)>>,
+}
+
+impl Dedup {
+    /// Whether the same file, or a copy of it, has been seen before in the same language. Empty
+    /// files are never duplicates, they are all alike.
+    pub fn is_duplicate(&self, path: &Path, lang: &'static str, bytes: &[u8]) -> bool {
+        if bytes.is_empty() {
+            return false;
+        }
+        if let Some((dev, ino)) = inode(path) {
+            if !self.inodes.lock().unwrap().insert((lang, dev, ino)) {
+                return true;
+            }
+        }
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        !self
+            .contents
+            .lock()
+            .unwrap()
+            .insert((lang, hasher.finish(), bytes.len()))
+    }
+}
+
+#[cfg(unix)]
+fn inode(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = std::fs::metadata(path).ok()?;
+    (meta.nlink() > 1).then(|| (meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn inode(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
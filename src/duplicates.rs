@@ -0,0 +1,199 @@
+use crate::lines::lines;
+use crate::syntax::{LineClassifier, LineKind, Syntax};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where a copy of a duplicated block is, by physical line numbers starting at 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A block of code lines found in more than one place
+#[derive(Debug, Clone)]
+pub struct DuplicateBlock {
+    /// Code lines in the block
+    pub lines: usize,
+    pub locations: Vec<Location>,
+}
+
+/// The outcome of the duplicate pass over all counted files
+#[derive(Debug, Clone, Default)]
+pub struct Duplicates {
+    pub blocks: Vec<DuplicateBlock>,
+    /// Code lines covered by a duplicated window, keyed by language name
+    pub lines: BTreeMap<String, usize>,
+}
+
+/// The windows of one file, a hash and the physical line range of each
+#[derive(Debug)]
+struct File {
+    path: PathBuf,
+    lang: &'static str,
+    windows: Vec<(u64, usize, usize)>,
+}
+
+/// Collects hashed windows of consecutive code lines while files are counted, so that copies
+/// across files can be matched once the walk is over
+#[derive(Debug)]
+pub(crate) struct DuplicateFinder {
+    window: usize,
+    files: Mutex<Vec<File>>,
+}
+
+impl DuplicateFinder {
+    pub fn new(window: usize) -> Self {
+        DuplicateFinder {
+            window: window.max(1),
+            files: Mutex::new(vec![]),
+        }
+    }
+
+    /// Hash every window of code lines of a file. Lines are compared with their whitespace
+    /// collapsed, and lines of punctuation only, such as closing braces, are skipped.
+    pub fn add(&self, path: &Path, lang: &'static str, syntax: &'static Syntax, content: &str) {
+        let mut classifier = LineClassifier::new(syntax);
+        let code: Vec<(usize, String)> = lines(content)
+            .enumerate()
+            .filter(|(_, line)| classifier.classify(line) == LineKind::Code)
+            .filter(|(_, line)| line.chars().any(char::is_alphanumeric))
+            .map(|(i, line)| (i + 1, line.split_whitespace().collect::<Vec<_>>().join(" ")))
+            .collect();
+        let windows = code
+            .windows(self.window)
+            .map(|window| {
+                let mut hasher = DefaultHasher::new();
+                window.iter().for_each(|(_, line)| line.hash(&mut hasher));
+                (hasher.finish(), window[0].0, window[window.len() - 1].0)
+            })
+            .collect();
+        self.files.lock().unwrap().push(File {
+            path: path.to_owned(),
+            lang,
+            windows,
+        });
+    }
+
+    /// Match the windows of all files. A window is duplicated when it is found in at least two
+    /// files, runs of consecutive duplicated windows in a file make up one block, and blocks
+    /// sharing a window are copies of each other.
+    pub fn finish(&self) -> Duplicates {
+        let files = self.files.lock().unwrap();
+        // the number of files each window is found in
+        let mut seen: HashMap<u64, usize> = HashMap::new();
+        for file in files.iter() {
+            let hashes: HashSet<u64> = file.windows.iter().map(|(hash, _, _)| *hash).collect();
+            for hash in hashes {
+                *seen.entry(hash).or_default() += 1;
+            }
+        }
+
+        let mut duplicates = Duplicates::default();
+        // the lines and location of every run of duplicated windows
+        let mut runs: Vec<(usize, Location)> = vec![];
+        // runs sharing a window are linked, the root of each tree of links stands for a block
+        let mut parents: Vec<usize> = vec![];
+        // the first run each window is found in
+        let mut firsts: HashMap<u64, usize> = HashMap::new();
+        for file in files.iter() {
+            let duplicated: Vec<bool> = file.windows.iter().map(|(h, _, _)| seen[h] > 1).collect();
+            let mut covered = 0;
+            let mut i = 0;
+            while i < duplicated.len() {
+                if !duplicated[i] {
+                    i += 1;
+                    continue;
+                }
+                let run = duplicated[i..].iter().take_while(|d| **d).count();
+                let lines = run + self.window - 1;
+                covered += lines;
+                let (_, start, _) = file.windows[i];
+                let (_, _, end) = file.windows[i + run - 1];
+                let index = runs.len();
+                runs.push((
+                    lines,
+                    Location {
+                        path: file.path.clone(),
+                        start,
+                        end,
+                    },
+                ));
+                parents.push(index);
+                for (hash, _, _) in &file.windows[i..i + run] {
+                    let first = *firsts.entry(*hash).or_insert(index);
+                    let (a, b) = (root(&mut parents, first), root(&mut parents, index));
+                    parents[a.max(b)] = a.min(b);
+                }
+                i += run;
+            }
+            *duplicates.lines.entry(file.lang.to_owned()).or_default() += covered;
+        }
+
+        let mut blocks: HashMap<usize, DuplicateBlock> = HashMap::new();
+        for (index, (lines, location)) in runs.into_iter().enumerate() {
+            let block = blocks
+                .entry(root(&mut parents, index))
+                .or_insert_with(|| DuplicateBlock {
+                    lines,
+                    locations: vec![],
+                });
+            block.lines = block.lines.min(lines);
+            block.locations.push(location);
+        }
+
+        duplicates.blocks = blocks
+            .into_values()
+            .filter(|block| block.locations.len() > 1)
+            .collect();
+        duplicates.blocks.sort_by(|a, b| {
+            b.lines
+                .cmp(&a.lines)
+                .then(a.locations[0].path.cmp(&b.locations[0].path))
+        });
+        duplicates
+    }
+}
+
+/// The run that stands for all copies of run `i`, shortening the links on the way
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::PLAIN;
+
+    #[test]
+    fn copies_starting_at_different_windows_are_one_block() {
+        let finder = DuplicateFinder::new(2);
+        finder.add(
+            Path::new("a"),
+            "Text",
+            &PLAIN,
+            "one\ntwo\nthree\nfour\nfive\n",
+        );
+        finder.add(Path::new("b"), "Text", &PLAIN, "one\ntwo\nthree\nfour\n");
+        finder.add(Path::new("c"), "Text", &PLAIN, "two\nthree\nfour\nfive\n");
+        let duplicates = finder.finish();
+        assert_eq!(duplicates.blocks.len(), 1);
+        assert_eq!(duplicates.blocks[0].lines, 4);
+        let locations: Vec<(&Path, usize, usize)> = duplicates.blocks[0]
+            .locations
+            .iter()
+            .map(|l| (l.path.as_path(), l.start, l.end))
+            .collect();
+        let (a, b, c) = (Path::new("a"), Path::new("b"), Path::new("c"));
+        assert_eq!(locations, [(a, 1, 5), (b, 1, 4), (c, 1, 4)]);
+        assert_eq!(duplicates.lines["Text"], 13);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::codestats::CodeStats;
+use crate::counter::Options;
+use crate::language::Language;
+use crate::lines::lines;
+use crate::markers::Marker;
+use crate::syntax::{LineClassifier, LineKind};
+use serde_json::Value;
+
+/// How other languages are embedded in a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Embedding {
+    /// `<script>` and `<style>` blocks of HTML, Vue and Svelte files
+    Html,
+    /// Fenced code blocks of Markdown
+    Markdown,
+    /// Code and markdown cells of Jupyter notebooks
+    Notebook,
+}
+
+/// Count a file whose embedded blocks go to the children of its stats
+pub(crate) fn count(
+    content: &str,
+    lang: &Language,
+    embedding: Embedding,
+    options: &Options,
+    found: &mut Vec<Marker>,
+) -> CodeStats {
+    match embedding {
+        Embedding::Html => count_html(content, lang, options, found),
+        Embedding::Markdown => count_markdown(content, lang, options, found),
+        Embedding::Notebook => count_notebook(content, lang, options, found),
+    }
+}
+
+/// A block of another language that is open in the parent file
+struct Block {
+    lang: &'static Language,
+    classifier: LineClassifier<'static>,
+    close: String,
+}
+
+impl Block {
+    fn new(lang: &'static Language, close: String) -> Self {
+        Block {
+            lang,
+            classifier: LineClassifier::new(lang.syntax),
+            close,
+        }
+    }
+}
+
+fn count_html(
+    content: &str,
+    lang: &Language,
+    options: &Options,
+    found: &mut Vec<Marker>,
+) -> CodeStats {
+    let mut stats = CodeStats::new();
+    let mut classifier = LineClassifier::new(lang.syntax);
+    let mut block: Option<Block> = None;
+
+    for (i, line) in lines(content).enumerate() {
+        if let Some(b) = &mut block {
+            // the closing tag line belongs to the parent
+            if line.to_ascii_lowercase().contains(&b.close) {
+                block = None;
+            } else {
+                stats
+                    .child(b.lang.name)
+                    .count_line(&mut b.classifier, i + 1, line, options, found);
+                continue;
+            }
+        }
+        let kind = stats.count_line(&mut classifier, i + 1, line, options, found);
+        if kind == LineKind::Code {
+            block = open_tag(line);
+        }
+    }
+    stats
+}
+
+/// Start a `<script>` or `<style>` block unless it closes on the same line
+fn open_tag(line: &str) -> Option<Block> {
+    let line = line.to_ascii_lowercase();
+    let tags = [
+        ("<script", "</script>", "JavaScript"),
+        ("<style", "</style>", "CSS"),
+    ];
+    let (tag, close, default) = tags.into_iter().find(|(tag, _, _)| line.contains(tag))?;
+    let rest = &line[line.find(tag)?..];
+    if rest.contains(close) {
+        return None;
+    }
+
+    // `lang="ts"` in Vue and Svelte, `type="text/typescript"` in plain HTML
+    let lang = attribute(rest, "lang")
+        .or_else(|| attribute(rest, "type").map(|t| t.rsplit('/').next().unwrap_or(t)))
+        .and_then(Language::from_name)
+        .or_else(|| Language::from_name(default))?;
+    Some(Block::new(lang, close.to_owned()))
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{}=", name))? + name.len() + 1;
+    let value = tag[start..].trim_start_matches(['"', '\'']);
+    let end = value.find(['"', '\'', ' ', '>'])?;
+    Some(&value[..end])
+}
+
+fn count_markdown(
+    content: &str,
+    lang: &Language,
+    options: &Options,
+    found: &mut Vec<Marker>,
+) -> CodeStats {
+    let mut stats = CodeStats::new();
+    let mut classifier = LineClassifier::new(lang.syntax);
+    // the fence that opened the current code block, and the block if its language is known
+    let mut fence: Option<(String, Option<Block>)> = None;
+
+    for (i, line) in lines(content).enumerate() {
+        let trimmed = line.trim();
+        if let Some((marker, block)) = &mut fence {
+            if trimmed.starts_with(marker.as_str())
+                && trimmed.chars().all(|c| marker.starts_with(c))
+            {
+                fence = None;
+            } else if let Some(b) = block {
+                stats
+                    .child(b.lang.name)
+                    .count_line(&mut b.classifier, i + 1, line, options, found);
+                continue;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let fence_char = trimmed.chars().next().unwrap_or('`');
+            let marker: String = trimmed.chars().take_while(|c| *c == fence_char).collect();
+            // info strings look like `rust`, `rust,ignore` or `{.python}`
+            let info = trimmed[marker.len()..]
+                .trim_start_matches(['{', '.', ' '])
+                .split([',', ' ', '}'])
+                .next()
+                .unwrap_or("");
+            let block = Language::from_name(info).map(|lang| Block::new(lang, String::new()));
+            fence = Some((marker, block));
+        }
+        stats.count_line(&mut classifier, i + 1, line, options, found);
+    }
+    stats
+}
+
+fn count_notebook(
+    content: &str,
+    lang: &Language,
+    options: &Options,
+    found: &mut Vec<Marker>,
+) -> CodeStats {
+    let notebook: Value = match serde_json::from_str(content) {
+        Ok(notebook) => notebook,
+        Err(_) => return CodeStats::count_lines(lines(content), lang.syntax, options, found),
+    };
+    let metadata = &notebook["metadata"];
+    let kernel = metadata["kernelspec"]["language"]
+        .as_str()
+        .or_else(|| metadata["language_info"]["name"].as_str())
+        .and_then(Language::from_name)
+        .or_else(|| Language::from_name("python"));
+    let markdown = Language::from_name("markdown");
+
+    let mut stats = CodeStats::new();
+    for cell in notebook["cells"].as_array().into_iter().flatten() {
+        let source = match &cell["source"] {
+            Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+            Value::String(source) => source.clone(),
+            _ => continue,
+        };
+        let cell_lang = match cell["cell_type"].as_str() {
+            Some("code") => kernel,
+            Some("markdown") => markdown,
+            _ => None,
+        };
+        if let Some(cell_lang) = cell_lang {
+            let mut cell_stats = CodeStats::count_content(&source, cell_lang, options, found);
+            // code fences of markdown cells are children of the notebook too
+            for (name, child) in std::mem::take(&mut cell_stats.children) {
+                *stats.child(&name) += child;
+            }
+            *stats.child(cell_lang.name) += cell_stats;
+        }
+    }
+    stats
+}
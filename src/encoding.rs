@@ -0,0 +1,61 @@
+use std::borrow::Cow;
+
+/// The encoding a file's bytes were decoded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Byte-level fallback for anything that isn't valid UTF-8, e.g. legacy Latin-1 sources
+    Latin1,
+}
+
+/// Decode a file into text, sniffing the BOM first, so that no text file is dropped
+pub fn decode(bytes: &[u8]) -> (Cow<'_, str>, Encoding) {
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        if let Ok(text) = std::str::from_utf8(rest) {
+            return (Cow::Borrowed(text), Encoding::Utf8);
+        }
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        return (decode_utf16(rest, u16::from_le_bytes), Encoding::Utf16Le);
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        return (decode_utf16(rest, u16::from_be_bytes), Encoding::Utf16Be);
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (Cow::Borrowed(text), Encoding::Utf8),
+        Err(_) => (
+            Cow::Owned(bytes.iter().map(|&b| b as char).collect()),
+            Encoding::Latin1,
+        ),
+    }
+}
+
+/// Whether the first block of a file looks binary, it holds a NUL byte or too many control
+/// characters. UTF-16 files with a BOM are text.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.starts_with(b"\xFF\xFE") || bytes.starts_with(b"\xFE\xFF") {
+        return false;
+    }
+    let block = &bytes[..bytes.len().min(8192)];
+    if block.contains(&0) {
+        return true;
+    }
+    let controls = block
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    controls * 10 > block.len()
+}
+
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> Cow<'static, str> {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| to_unit([pair[0], pair[1]]));
+    Cow::Owned(
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+    )
+}
@@ -0,0 +1,80 @@
+use crate::counter::Options;
+use glob::{MatchOptions, Pattern};
+use std::path::{Component, Path};
+
+/// Directories that are never walked into
+const SKIPPED_DIRS: &[&str] = &[".git", ".repo", "node_modules", "target"];
+
+/// Narrows a walk by the default skip list and the `--exclude` and `--include` patterns
+#[derive(Debug)]
+pub(crate) struct Filter {
+    excludes: Vec<Pattern>,
+    includes: Vec<Pattern>,
+}
+
+impl Filter {
+    pub fn new(options: &Options) -> Self {
+        Filter {
+            excludes: options.excludes.clone(),
+            includes: options.includes.clone(),
+        }
+    }
+
+    /// Whether a path below the walk root is skipped. Patterns match the path below any of its
+    /// parents, so `prebuilts/**` leaves out every `prebuilts` directory, and `*` doesn't match
+    /// a `/`.
+    /// Include patterns only apply to files, directories are walked to find them.
+    pub fn is_skipped(&self, path: &Path, is_dir: bool) -> bool {
+        let names: Vec<_> = path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect();
+        let skipped_dir = match is_dir {
+            true => &names[..],
+            false => &names[..names.len().saturating_sub(1)],
+        };
+        if skipped_dir
+            .iter()
+            .any(|name| SKIPPED_DIRS.contains(&name.as_ref()))
+        {
+            return true;
+        }
+
+        let suffixes: Vec<String> = (0..names.len()).map(|i| names[i..].join("/")).collect();
+        let matches = |patterns: &[Pattern]| {
+            let options = MatchOptions {
+                require_literal_separator: true,
+                ..Default::default()
+            };
+            suffixes.iter().any(|suffix| {
+                patterns.iter().any(|p| {
+                    p.matches_with(suffix, options)
+                        || (is_dir && p.matches_with(&format!("{}/", suffix), options))
+                })
+            })
+        };
+        matches(&self.excludes)
+            || (!is_dir && !self.includes.is_empty() && !matches(&self.includes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::counter::{Counter, Options};
+    use std::path::Path;
+
+    #[test]
+    fn skipped_dir_above_the_root_does_not_hide_the_walk() {
+        let options = Options {
+            no_ignore: true,
+            ..Default::default()
+        };
+        let counter = Counter::new(options, &[Path::new("/work/target/project")]);
+        assert!(!counter.is_ignored(Path::new("/work/target/project/src/main.rs"), false));
+        assert!(counter.is_ignored(Path::new("/work/target/project/target"), true));
+        assert!(counter.is_ignored(Path::new("/work/target/project/target/out.rs"), false));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::counter::Options;
+use crate::lines::lines;
+use glob::Pattern;
+use std::path::Path;
+
+/// Header markers of generated files, matched ignoring case
+const MARKERS: &[&str] = &[
+    "@generated",
+    "do not edit",
+    "do not modify",
+    "auto-generated",
+    "autogenerated",
+    "code generated by",
+];
+
+/// Path patterns of generated files such as protobuf and resource stubs
+const PATHS: &[&str] = &[
+    "*.pb.cc",
+    "*.pb.h",
+    "*.pb.go",
+    "*_pb2.py",
+    "*_pb2_grpc.py",
+    "**/R.java",
+    "*.g.dart",
+    "*.designer.cs",
+];
+
+/// How many lines of a file are searched for header markers
+const HEADER_LINES: usize = 10;
+
+/// Tells generated files apart from hand-written code
+#[derive(Debug)]
+pub(crate) struct Generated {
+    markers: Vec<String>,
+    paths: Vec<Pattern>,
+}
+
+impl Generated {
+    pub fn new(options: &Options) -> Self {
+        let markers = MARKERS
+            .iter()
+            .map(|m| m.to_string())
+            .chain(options.generated_markers.iter().map(|m| m.to_lowercase()))
+            .collect();
+        let paths = PATHS
+            .iter()
+            .map(|p| Pattern::new(p).unwrap())
+            .chain(options.generated_paths.iter().cloned())
+            .collect();
+        Generated { markers, paths }
+    }
+
+    /// Generated by a path pattern, which is matched against the path below the walk root, or by
+    /// a header marker
+    pub fn is_generated(&self, relative: &Path, content: &str) -> bool {
+        if self.paths.iter().any(|p| p.matches_path(relative)) {
+            return true;
+        }
+        lines(content).take(HEADER_LINES).any(|line| {
+            let line = line.to_lowercase();
+            self.markers.iter().any(|m| line.contains(m.as_str()))
+        })
+    }
+}
@@ -0,0 +1,230 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Files of ignore rules in a directory, the first one that matches wins
+const IGNORE_FILES: &[&str] = &[".ignore", ".gitignore"];
+
+/// The ignore rules of a directory
+#[derive(Debug)]
+struct Dir {
+    matchers: Vec<Gitignore>,
+    /// Whether the directory is the root of a git repository, where the search for rules stops
+    repo_root: bool,
+}
+
+/// The directories above a walk root whose rules still apply, when the root is inside a git
+/// repository
+#[derive(Debug)]
+struct Above {
+    /// The canonical root, paths below it are matched as absolute paths
+    root: PathBuf,
+    /// From the root's parent up to the repository root
+    dirs: Vec<PathBuf>,
+}
+
+/// Honors the `.ignore` and `.gitignore` files of every directory up to the repository root,
+/// the repository's `.git/info/exclude` and the global git excludes. Only the part of a path
+/// below the walk root is matched, an ignored directory above it doesn't hide the walk.
+#[derive(Debug)]
+pub(crate) struct GitIgnore {
+    global: Gitignore,
+    /// Rules of every directory seen so far
+    dirs: Mutex<HashMap<PathBuf, Arc<Dir>>>,
+    above: HashMap<PathBuf, Above>,
+}
+
+impl GitIgnore {
+    pub fn new(roots: &[PathBuf]) -> Self {
+        GitIgnore {
+            global: Gitignore::global().0,
+            dirs: Mutex::new(HashMap::new()),
+            above: roots
+                .iter()
+                .filter_map(|root| Some((root.clone(), above(root)?)))
+                .collect(),
+        }
+    }
+
+    /// Whether the rules of the nearest directory that has an opinion ignore a path or one of
+    /// its parents below the walk root
+    pub fn is_ignored(&self, path: &Path, root: Option<&Path>, is_dir: bool) -> bool {
+        let mut in_repo = false;
+        for dir in path.ancestors().skip(1) {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            let rules = self.dir(dir);
+            for matcher in &rules.matchers {
+                match matcher.matched_path_or_any_parents(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+            if rules.repo_root {
+                in_repo = true;
+                break;
+            }
+            if root == Some(dir) {
+                break;
+            }
+        }
+
+        let relative = root
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        let above = root.and_then(|root| self.above.get(root));
+        if let (false, Some(above)) = (in_repo, above) {
+            let path = above.root.join(relative);
+            for dir in &above.dirs {
+                for matcher in &self.dir(dir).matchers {
+                    match matched_below(matcher, &path, &above.root, is_dir) {
+                        Match::Ignore(_) => return true,
+                        Match::Whitelist(_) => return false,
+                        Match::None => {}
+                    }
+                }
+            }
+        }
+
+        // global excludes are mostly file name patterns like `*.swp`
+        self.global.num_ignores() > 0
+            && relative
+                .ancestors()
+                .filter(|p| !p.as_os_str().is_empty())
+                .enumerate()
+                .any(|(i, p)| self.global.matched(p, i > 0 || is_dir).is_ignore())
+    }
+
+    fn dir(&self, dir: &Path) -> Arc<Dir> {
+        let mut dirs = self.dirs.lock().unwrap();
+        dirs.entry(dir.to_owned())
+            .or_insert_with(|| {
+                let repo_root = dir.join(".git").is_dir();
+                let exclude = repo_root.then(|| dir.join(".git/info/exclude"));
+                let matchers = IGNORE_FILES
+                    .iter()
+                    .map(|name| dir.join(name))
+                    .chain(exclude)
+                    .filter(|file| file.is_file())
+                    .filter_map(|file| build(dir, &file))
+                    .collect();
+                Arc::new(Dir {
+                    matchers,
+                    repo_root,
+                })
+            })
+            .clone()
+    }
+}
+
+/// The directories above a root up to the root of the repository it is in, if any
+fn above(root: &Path) -> Option<Above> {
+    let root = root.canonicalize().ok()?;
+    let mut dirs = vec![];
+    for dir in root.ancestors().skip(1) {
+        dirs.push(dir.to_owned());
+        if dir.join(".git").is_dir() {
+            return Some(Above { root, dirs });
+        }
+    }
+    None
+}
+
+/// Match a path and then its parents, as long as they are below `root`
+fn matched_below<'a>(
+    matcher: &'a Gitignore,
+    path: &Path,
+    root: &Path,
+    is_dir: bool,
+) -> Match<&'a ignore::gitignore::Glob> {
+    path.ancestors()
+        .take_while(|p| *p != root)
+        .enumerate()
+        .map(|(i, p)| matcher.matched(p, i > 0 || is_dir))
+        .find(|m| !m.is_none())
+        .unwrap_or(Match::None)
+}
+
+fn build(root: &Path, file: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    builder.add(file);
+    builder.build().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A fresh directory under the temp dir with `files` written below it
+    fn tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("code_stats_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn ignored_dir_above_the_root_does_not_hide_the_walk() {
+        let repo = tree(
+            "above",
+            &[
+                (".git/HEAD", ""),
+                (".gitignore", "out/\n*.log\n"),
+                ("out/project/main.rs", ""),
+                ("out/project/debug.log", ""),
+            ],
+        );
+        let root = repo.join("out/project");
+        let gitignore = GitIgnore::new(std::slice::from_ref(&root));
+        let ignored = |name: &str| gitignore.is_ignored(&root.join(name), Some(&root), false);
+        assert!(!ignored("main.rs"));
+        assert!(ignored("debug.log"));
+        fs::remove_dir_all(repo).unwrap();
+    }
+
+    #[test]
+    fn ignore_file_wins_over_gitignore() {
+        let root = tree(
+            "precedence",
+            &[
+                (".gitignore", "*.rs\n"),
+                (".ignore", "!keep.rs\n"),
+                ("keep.rs", ""),
+                ("drop.rs", ""),
+            ],
+        );
+        let gitignore = GitIgnore::new(std::slice::from_ref(&root));
+        let ignored = |name: &str| gitignore.is_ignored(&root.join(name), Some(&root), false);
+        assert!(!ignored("keep.rs"));
+        assert!(ignored("drop.rs"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn negated_pattern_keeps_a_file() {
+        let root = tree(
+            "negation",
+            &[
+                (".gitignore", "*.log\n!keep.log\n"),
+                ("keep.log", ""),
+                ("drop.log", ""),
+            ],
+        );
+        let gitignore = GitIgnore::new(std::slice::from_ref(&root));
+        let ignored = |name: &str| gitignore.is_ignored(&root.join(name), Some(&root), false);
+        assert!(!ignored("keep.log"));
+        assert!(ignored("drop.log"));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,550 @@
+use crate::custom;
+use crate::embedded::Embedding;
+use crate::encoding;
+use crate::lines;
+use crate::syntax::{self, Syntax};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// A named language and the files that belong to it
+#[derive(Debug)]
+pub struct Language {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub filenames: &'static [&'static str],
+    pub aliases: &'static [&'static str],
+    pub syntax: &'static Syntax,
+    /// How other languages are embedded in files of this language
+    pub embedding: Option<Embedding>,
+}
+
+impl PartialEq for Language {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Language {}
+
+macro_rules! language {
+    ($name:expr, [$($ext:expr),*], [$($file:expr),*], [$($alias:expr),*], $syntax:expr) => {
+        language!($name, [$($ext),*], [$($file),*], [$($alias),*], $syntax, None)
+    };
+    ($name:expr, [$($ext:expr),*], [$($file:expr),*], [$($alias:expr),*], $syntax:expr, $embedding:expr) => {
+        Language {
+            name: $name,
+            extensions: &[$($ext),*],
+            filenames: &[$($file),*],
+            aliases: &[$($alias),*],
+            syntax: &$syntax,
+            embedding: $embedding,
+        }
+    };
+}
+
+/// The registry of known languages
+static LANGUAGES: &[Language] = &[
+    language!("C", ["c", "h"], [], [], syntax::C_STYLE),
+    language!(
+        "C++",
+        ["cc", "cpp", "cxx", "c++", "hh", "hpp", "hxx", "inl"],
+        [],
+        ["cplusplus"],
+        syntax::CPP
+    ),
+    language!("C#", ["cs"], [], ["csharp"], syntax::JAVA),
+    language!("CMake", ["cmake"], ["CMakeLists.txt"], [], syntax::HASH),
+    language!("CSS", ["css"], [], [], syntax::CSS),
+    language!("Dart", ["dart"], [], [], syntax::JAVA),
+    language!(
+        "Dockerfile",
+        ["dockerfile"],
+        ["Dockerfile"],
+        ["docker"],
+        syntax::HASH
+    ),
+    language!("Go", ["go"], [], ["golang"], syntax::GO),
+    language!("Groovy", ["groovy", "gradle"], [], [], syntax::JAVA),
+    language!(
+        "HTML",
+        ["html", "htm"],
+        [],
+        [],
+        syntax::MARKUP,
+        Some(Embedding::Html)
+    ),
+    language!("Java", ["java"], [], [], syntax::JAVA),
+    language!(
+        "JavaScript",
+        ["js", "mjs", "cjs"],
+        [],
+        ["node", "nodejs"],
+        syntax::JAVASCRIPT
+    ),
+    language!("JSON", ["json"], [], [], syntax::PLAIN),
+    language!(
+        "Jupyter Notebooks",
+        ["ipynb"],
+        [],
+        ["jupyter"],
+        syntax::PLAIN,
+        Some(Embedding::Notebook)
+    ),
+    language!("Kotlin", ["kt", "kts"], [], [], syntax::KOTLIN),
+    language!("Less", ["less"], [], [], syntax::C_STYLE),
+    language!("Lua", ["lua"], [], [], syntax::LUA),
+    language!("MATLAB", [], [], [], syntax::MATLAB),
+    language!(
+        "Makefile",
+        ["mk", "mak"],
+        ["Makefile", "GNUmakefile", "makefile"],
+        ["make"],
+        syntax::HASH
+    ),
+    language!(
+        "Markdown",
+        ["md", "markdown"],
+        [],
+        [],
+        syntax::MARKUP,
+        Some(Embedding::Markdown)
+    ),
+    language!("Objective-C", ["m"], [], ["objc"], syntax::C_STYLE),
+    language!("Objective-C++", ["mm"], [], ["objcpp"], syntax::CPP),
+    language!("Perl", ["pl", "pm"], [], [], syntax::SHELL),
+    language!("PHP", ["php"], [], [], syntax::PHP),
+    language!("Prolog", ["prolog"], [], [], syntax::PROLOG),
+    language!("Properties", ["properties"], [], [], syntax::HASH),
+    language!(
+        "Protocol Buffers",
+        ["proto"],
+        [],
+        ["protobuf"],
+        syntax::C_STYLE
+    ),
+    language!(
+        "Python",
+        ["py", "pyw", "pyi"],
+        [],
+        ["python2", "python3"],
+        syntax::PYTHON
+    ),
+    language!("Qt Translation", [], [], ["qt-linguist"], syntax::MARKUP),
+    language!("R", ["r"], [], [], syntax::SHELL),
+    language!("Ruby", ["rb"], ["Rakefile", "Gemfile"], [], syntax::SHELL),
+    language!("Rust", ["rs"], [], [], syntax::RUST),
+    language!("Scala", ["scala"], [], [], syntax::KOTLIN),
+    language!("SCSS", ["scss"], [], [], syntax::C_STYLE),
+    language!(
+        "Shell",
+        ["sh", "bash", "zsh"],
+        [],
+        ["bash", "zsh", "dash", "ksh"],
+        syntax::SHELL
+    ),
+    language!("SQL", ["sql"], [], [], syntax::SQL),
+    language!(
+        "Svelte",
+        ["svelte"],
+        [],
+        [],
+        syntax::MARKUP,
+        Some(Embedding::Html)
+    ),
+    language!("SVG", ["svg"], [], [], syntax::MARKUP),
+    language!("Swift", ["swift"], [], [], syntax::SWIFT),
+    language!("TOML", ["toml"], [], [], syntax::HASH),
+    language!("TypeScript", ["ts", "tsx"], [], [], syntax::JAVASCRIPT),
+    language!(
+        "Vue",
+        ["vue"],
+        [],
+        [],
+        syntax::MARKUP,
+        Some(Embedding::Html)
+    ),
+    language!("XML", ["xml"], [], [], syntax::MARKUP),
+    language!("YAML", ["yaml", "yml"], [], [], syntax::HASH),
+];
+
+/// Languages declared by the user, they take precedence over the built-in ones
+static CUSTOM: OnceLock<Vec<Language>> = OnceLock::new();
+
+/// Plain text languages for the `-t` types that name no other language, see `select`
+static PLAIN_TYPES: OnceLock<Vec<Language>> = OnceLock::new();
+
+/// Files whose language is only known from their content, by the glob pattern that found them.
+/// Every language globbing the same tree shares them, so each file is read once per run.
+static DETECTED: OnceLock<Mutex<HashMap<String, Detected>>> = OnceLock::new();
+
+type Detected = Arc<Vec<(PathBuf, &'static Language)>>;
+
+/// The user's languages followed by the built-in ones
+fn languages() -> impl Iterator<Item = &'static Language> {
+    CUSTOM
+        .get()
+        .into_iter()
+        .flatten()
+        .chain(LANGUAGES.iter())
+        .chain(PLAIN_TYPES.get().into_iter().flatten())
+}
+
+/// A test on a trimmed line of a file
+type LineTest = fn(&str) -> bool;
+
+/// Content rules for an extension that is shared by several languages
+struct Heuristic {
+    extension: &'static str,
+    /// Languages and a test on the trimmed lines of a file, the first language that has a
+    /// matching line wins
+    rules: &'static [(&'static str, LineTest)],
+    /// The language when no rule matches, if any
+    default: Option<&'static str>,
+    /// Whether binary files with the extension are another format altogether, like MPEG
+    /// transport streams for `.ts`, instead of source files the counter records as binaries
+    binary_format: bool,
+}
+
+static HEURISTICS: &[Heuristic] = &[
+    Heuristic {
+        extension: "h",
+        rules: &[("Objective-C", objc_line), ("C++", cpp_line)],
+        default: Some("C"),
+        binary_format: false,
+    },
+    Heuristic {
+        extension: "inc",
+        rules: &[("PHP", php_line), ("C++", preprocessor_line)],
+        default: None,
+        binary_format: false,
+    },
+    Heuristic {
+        extension: "m",
+        rules: &[("Objective-C", objc_line), ("MATLAB", matlab_line)],
+        default: Some("Objective-C"),
+        binary_format: false,
+    },
+    Heuristic {
+        extension: "pl",
+        rules: &[("Prolog", prolog_line)],
+        default: Some("Perl"),
+        binary_format: false,
+    },
+    Heuristic {
+        extension: "ts",
+        rules: &[("Qt Translation", qt_line)],
+        default: Some("TypeScript"),
+        binary_format: true,
+    },
+];
+
+impl Heuristic {
+    /// The rules of a shared extension, unless the user has declared a language for it
+    fn of(ext: &str) -> Option<&'static Heuristic> {
+        let ext = ext.to_lowercase();
+        let custom = CUSTOM.get().into_iter().flatten();
+        if custom
+            .clone()
+            .any(|lang| lang.extensions.contains(&ext.as_str()))
+        {
+            return None;
+        }
+        HEURISTICS.iter().find(|h| h.extension == ext)
+    }
+
+    fn candidates(&self) -> impl Iterator<Item = &'static str> {
+        self.rules.iter().map(|(name, _)| *name).chain(self.default)
+    }
+
+    /// Pick the language from the head of a file. Binary files fall back to the default so they
+    /// are recorded as binaries, unless the extension has a binary format of its own.
+    fn pick(&self, head: &[u8]) -> Option<&'static Language> {
+        if encoding::is_binary(head) {
+            return match self.binary_format {
+                true => None,
+                false => self.default.and_then(Language::from_name),
+            };
+        }
+        let text = String::from_utf8_lossy(head);
+        let lines: Vec<&str> = lines::lines(&text).map(str::trim).collect();
+        self.rules
+            .iter()
+            .find(|(_, test)| lines.iter().any(|line| test(line)))
+            .map(|(name, _)| *name)
+            .or(self.default)
+            .and_then(Language::from_name)
+    }
+}
+
+impl Language {
+    /// Add the languages of a TOML config file to the registry, once at startup before any
+    /// language is looked up
+    pub fn load(path: &Path) -> anyhow::Result<()> {
+        CUSTOM
+            .set(custom::read(path)?)
+            .map_err(|_| anyhow::anyhow!("languages are already loaded"))
+    }
+
+    /// Find a language by its name, an alias or one of its extensions, ignoring case
+    pub fn from_name(name: &str) -> Option<&'static Language> {
+        let name = name.to_lowercase();
+        languages()
+            .find(|lang| lang.name.to_lowercase() == name || lang.aliases.contains(&name.as_str()))
+            .or_else(|| Language::from_ext(&name))
+    }
+
+    pub fn from_ext(ext: &str) -> Option<&'static Language> {
+        let ext = ext.to_lowercase();
+        languages().find(|lang| lang.extensions.contains(&ext.as_str()))
+    }
+
+    /// Detect the language of a file, exact file names take precedence over extensions
+    pub fn from_path(path: &Path) -> Option<&'static Language> {
+        let file_name = path.file_name().and_then(OsStr::to_str)?;
+        languages()
+            .find(|lang| lang.filenames.contains(&file_name))
+            .or_else(|| Language::from_ext(path.extension().and_then(OsStr::to_str)?))
+    }
+
+    /// Detect the language of a file by its path, extension-less files like `bin/` scripts are
+    /// detected by their shebang or modeline, and files with an extension shared by several
+    /// languages like `.h` by their content
+    pub fn detect(path: &Path) -> Option<&'static Language> {
+        let ext = path.extension().and_then(OsStr::to_str);
+        if let Some(heuristic) = ext.and_then(Heuristic::of) {
+            return heuristic.pick(&read_head(path)?);
+        }
+        match Language::from_path(path) {
+            Some(lang) => Some(lang),
+            None if ext.is_none() => {
+                Language::from_content(&String::from_utf8_lossy(&read_head(path)?))
+            }
+            None => None,
+        }
+    }
+
+    /// Detect the language from a `#!` shebang, an emacs modeline on the first lines or a vim
+    /// modeline on the first or last five lines
+    pub fn from_content(content: &str) -> Option<&'static Language> {
+        let lines: Vec<&str> = lines::lines(content).collect();
+        let head = lines.iter().take(5);
+        let tail = lines.iter().skip(5).rev().take(5);
+
+        lines
+            .first()
+            .and_then(|line| shebang(line))
+            .or_else(|| lines.iter().take(2).find_map(|line| emacs_mode(line)))
+            .or_else(|| head.chain(tail).find_map(|line| vim_filetype(line)))
+            .and_then(Language::from_interpreter)
+    }
+
+    /// Like `from_name`, but ignores version suffixes such as `python3.8`
+    fn from_interpreter(name: &str) -> Option<&'static Language> {
+        Language::from_name(name).or_else(|| {
+            Language::from_name(name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+        })
+    }
+
+    /// Resolve the `-t` arguments of the command line into a list of distinct languages, once at
+    /// startup after `load`. A type that names no language counts the files with it as their
+    /// extension as plain text, like `-t bp` for `*.bp`.
+    pub fn select(types: &[String]) -> anyhow::Result<Vec<&'static Language>> {
+        PLAIN_TYPES.get_or_init(|| {
+            let mut plain: Vec<Language> = vec![];
+            for t in types {
+                let known = Language::from_name(t).is_some()
+                    || plain.iter().any(|lang| lang.name.eq_ignore_ascii_case(t));
+                if !known {
+                    plain.push(custom::plain(t));
+                }
+            }
+            plain
+        });
+        let mut langs: Vec<&'static Language> = vec![];
+        for t in types {
+            let lang = Language::from_name(t)
+                .ok_or_else(|| anyhow::anyhow!("unknown language type: {}", t))?;
+            if !langs.contains(&lang) {
+                langs.push(lang);
+            }
+        }
+        Ok(langs)
+    }
+
+    /// Glob all the files of this language under `dir` that `keep` lets through, including
+    /// extension-less scripts and the files of shared extensions that are detected as this
+    /// language. Files are filtered before their content is read for detection, and detected
+    /// files are shared by all languages globbing the same `dir`, so `keep` should be the same
+    /// for all of them.
+    pub fn glob<'a, F>(
+        &'a self,
+        dir: &Path,
+        keep: F,
+    ) -> Result<impl Iterator<Item = PathBuf> + 'a, glob::PatternError>
+    where
+        F: Fn(&Path) -> bool + Copy + 'a,
+    {
+        let dir = dir.to_string_lossy();
+        let paths = self
+            .extensions
+            .iter()
+            .filter(|ext| Heuristic::of(ext).is_none())
+            .map(|ext| format!("{}**/*.{}", dir, ext))
+            .chain(
+                // file names with one of the extensions are globbed already
+                self.filenames
+                    .iter()
+                    .filter(|name| {
+                        let ext = Path::new(name).extension().and_then(OsStr::to_str);
+                        !ext.is_some_and(|ext| self.extensions.contains(&ext))
+                    })
+                    .map(|name| format!("{}**/{}", dir, name)),
+            )
+            .map(|pattern| glob::glob(&pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        let shared = HEURISTICS
+            .iter()
+            .filter(|h| Heuristic::of(h.extension).is_some())
+            .filter(|h| h.candidates().any(|name| name == self.name))
+            .map(|h| detect_all(&format!("{}**/*.{}", dir, h.extension), keep))
+            .collect::<Result<Vec<_>, _>>()?;
+        let script = move |p: &Path| {
+            p.extension().is_none() && Language::from_path(p).is_none() && p.is_file() && keep(p)
+        };
+        let scripts = detect_all(&format!("{}**/*", dir), script)?;
+        let detected = shared
+            .into_iter()
+            .chain([scripts])
+            .flat_map(move |detected| {
+                detected
+                    .iter()
+                    .filter(|(_, lang)| *lang == self)
+                    .map(|(path, _)| path.clone())
+                    .collect::<Vec<_>>()
+            });
+        Ok(paths
+            .into_iter()
+            .flatten()
+            .filter_map(|p| p.ok())
+            .filter(move |p| keep(p))
+            .chain(detected))
+    }
+}
+
+/// Detect the language of every file of a glob pattern that `keep` lets through, once per run
+fn detect_all(pattern: &str, keep: impl Fn(&Path) -> bool) -> Result<Detected, glob::PatternError> {
+    let cache = DETECTED.get_or_init(Default::default);
+    // held while globbing, so that languages asking for the same pattern wait for the result
+    let mut cache = cache.lock().unwrap();
+    if let Some(detected) = cache.get(pattern) {
+        return Ok(detected.clone());
+    }
+    let detected: Detected = Arc::new(
+        glob::glob(pattern)?
+            .filter_map(|p| p.ok())
+            .filter(|p| keep(p))
+            .filter_map(|p| Some((p.clone(), Language::detect(&p)?)))
+            .collect(),
+    );
+    cache.insert(pattern.to_owned(), detected.clone());
+    Ok(detected)
+}
+
+/// Read the first block of a file, enough for shebangs, modelines and content heuristics
+fn read_head(path: &Path) -> Option<Vec<u8>> {
+    let mut head = vec![];
+    File::open(path)
+        .ok()?
+        .take(4096)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some(head)
+}
+
+fn objc_line(line: &str) -> bool {
+    [
+        "#import",
+        "@interface",
+        "@implementation",
+        "@protocol",
+        "@end",
+        "@property",
+    ]
+    .iter()
+    .any(|prefix| line.starts_with(prefix))
+}
+
+fn cpp_line(line: &str) -> bool {
+    let prefixes = [
+        "class ",
+        "namespace ",
+        "template",
+        "public:",
+        "private:",
+        "protected:",
+        "using ",
+    ];
+    prefixes.iter().any(|prefix| line.starts_with(prefix)) || line.contains("std::")
+}
+
+fn matlab_line(line: &str) -> bool {
+    line.starts_with("function ") || line.starts_with('%') || line == "end"
+}
+
+fn prolog_line(line: &str) -> bool {
+    line.starts_with(":-") || line.ends_with(":-")
+}
+
+fn php_line(line: &str) -> bool {
+    line.starts_with("<?php") || line.starts_with("<?=")
+}
+
+fn preprocessor_line(line: &str) -> bool {
+    ["#include", "#define", "#if", "#pragma"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+fn qt_line(line: &str) -> bool {
+    line.starts_with("<!DOCTYPE TS") || line.starts_with("<TS")
+}
+
+/// The interpreter of `#!/bin/bash -e` or `#!/usr/bin/env -S python3 -u`
+fn shebang(line: &str) -> Option<&str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        words.find(|word| !word.starts_with('-') && !word.contains('='))
+    } else {
+        Some(program)
+    }
+}
+
+/// The mode of `-*- python -*-` or `-*- mode: python; coding: utf-8 -*-`
+fn emacs_mode(line: &str) -> Option<&str> {
+    let start = line.find("-*-")? + 3;
+    let end = start + line[start..].find("-*-")?;
+    let vars = line[start..end].trim();
+    if !vars.contains(':') {
+        return Some(vars);
+    }
+    vars.split(';').find_map(|var| {
+        let (key, value) = var.split_once(':')?;
+        (key.trim().eq_ignore_ascii_case("mode")).then(|| value.trim())
+    })
+}
+
+/// The filetype of `vim: set ft=python:` or `vi: filetype=sh`
+fn vim_filetype(line: &str) -> Option<&str> {
+    let start = ["vim:", "vi:", "ex:"]
+        .iter()
+        .find_map(|marker| line.find(marker).map(|idx| idx + marker.len()))?;
+    line[start..].split([' ', ':', '\t']).find_map(|opt| {
+        let (key, value) = opt.split_once('=')?;
+        matches!(key, "ft" | "filetype" | "syntax").then_some(value)
+    })
+}
@@ -4,5 +4,47 @@ pub use tc_runner::TcRunner;
 mod codestats;
 pub use codestats::CodeStats;
 
+mod syntax;
+pub use syntax::{LineClassifier, LineKind, Syntax};
+
+mod language;
+pub use language::Language;
+
+mod custom;
+
+mod lines;
+pub use lines::{lines, LineEnding, Lines};
+
+mod encoding;
+pub use encoding::{decode, is_binary, Encoding};
+
+mod embedded;
+pub use embedded::Embedding;
+
+mod dedup;
+mod filter;
+mod generated;
+mod gitignore;
+mod testcode;
+mod vendored;
+
+mod duplicates;
+pub use duplicates::{DuplicateBlock, Duplicates, Location};
+
+mod markers;
+pub use markers::Marker;
+
+mod license;
+pub use license::NO_LICENSE;
+
+mod place;
+pub use place::{by_group, by_language, Key, Place};
+
+mod counter;
+pub use counter::{Counter, Options};
+
+mod report;
+pub use report::{print_duplicates, print_groups, print_markers, print_table};
+
 mod cli;
 pub use cli::Cli;
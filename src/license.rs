@@ -0,0 +1,81 @@
+use crate::lines::lines;
+use crate::syntax::{LineClassifier, LineKind, Syntax};
+
+/// The bucket of files without any license header
+pub const NO_LICENSE: &str = "No license header";
+
+/// How many lines of a file are searched for its first comment block
+const HEADER_LINES: usize = 100;
+
+/// Common license texts and the SPDX identifier they stand for, most specific first
+const TEXTS: &[(&str, &str)] = &[
+    ("apache license, version 2.0", "Apache-2.0"),
+    ("apache license version 2.0", "Apache-2.0"),
+    ("gnu lesser general public license", "LGPL"),
+    ("gnu library general public license", "LGPL"),
+    ("gnu affero general public license", "AGPL"),
+    ("gnu general public license", "GPL"),
+    ("permission is hereby granted, free of charge", "MIT"),
+    ("redistribution and use in source and binary forms", "BSD"),
+];
+
+/// The license of a file from an `SPDX-License-Identifier` tag or a well-known license text in
+/// its first comment block
+pub(crate) fn detect(content: &str, syntax: &'static Syntax) -> Option<String> {
+    let mut classifier = LineClassifier::new(syntax);
+    let mut header = String::new();
+    for line in lines(content).take(HEADER_LINES) {
+        match classifier.classify(line) {
+            LineKind::Code => break,
+            LineKind::Blank => {}
+            LineKind::Comment | LineKind::Doc => {
+                let comment = classifier.comment();
+                if let Some(spdx) = spdx(&comment) {
+                    return Some(spdx);
+                }
+                header.push_str(comment.trim());
+                header.push(' ');
+            }
+        }
+    }
+
+    let header = header
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let (_, license) = TEXTS.iter().find(|(text, _)| header.contains(text))?;
+    let license = match *license {
+        "GPL" | "LGPL" | "AGPL" => match version(&header) {
+            Some(version) => format!("{}-{}", license, version),
+            None => license.to_string(),
+        },
+        "BSD" if header.contains("neither the name") => "BSD-3-Clause".to_owned(),
+        "BSD" => "BSD-2-Clause".to_owned(),
+        _ => license.to_string(),
+    };
+    Some(license)
+}
+
+/// The expression of `SPDX-License-Identifier: Apache-2.0 OR MIT`
+fn spdx(comment: &str) -> Option<String> {
+    let tag = "SPDX-License-Identifier:";
+    let start = comment.find(tag)? + tag.len();
+    let expression = comment[start..].trim().trim_end_matches("*/").trim();
+    (!expression.is_empty()).then(|| expression.to_owned())
+}
+
+/// The version of `version 2 of the License` or `either version 2.1`
+fn version(header: &str) -> Option<String> {
+    let start = header.find("version ")? + "version ".len();
+    let number: String = header[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let number = number.trim_end_matches('.');
+    match number {
+        "" => None,
+        _ if number.contains('.') => Some(number.to_owned()),
+        _ => Some(format!("{}.0", number)),
+    }
+}
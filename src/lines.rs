@@ -0,0 +1,108 @@
+/// Split text into lines. A line ends at `\n`, `\r\n` or a lone `\r`, a final line without a line
+/// break is still a line, and an empty text has no lines.
+pub fn lines(text: &str) -> Lines<'_> {
+    Lines { rest: text }
+}
+
+pub struct Lines<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let line = match self.rest.find(['\n', '\r']) {
+            Some(idx) => {
+                let line = &self.rest[..idx];
+                let ending = if self.rest[idx..].starts_with("\r\n") {
+                    2
+                } else {
+                    1
+                };
+                self.rest = &self.rest[idx + ending..];
+                line
+            }
+            None => std::mem::take(&mut self.rest),
+        };
+        Some(line)
+    }
+}
+
+/// The line breaks used in a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// A single line without any line break
+    None,
+    Lf,
+    CrLf,
+    /// Classic Mac OS line breaks
+    Cr,
+    Mixed,
+}
+
+impl LineEnding {
+    pub fn detect(text: &str) -> LineEnding {
+        let (mut lf, mut crlf, mut cr) = (false, false, false);
+        let mut bytes = text.bytes().peekable();
+        while let Some(b) = bytes.next() {
+            match b {
+                b'\n' => lf = true,
+                b'\r' if bytes.peek() == Some(&b'\n') => {
+                    bytes.next();
+                    crlf = true;
+                }
+                b'\r' => cr = true,
+                _ => {}
+            }
+        }
+        match (lf, crlf, cr) {
+            (false, false, false) => LineEnding::None,
+            (true, false, false) => LineEnding::Lf,
+            (false, true, false) => LineEnding::CrLf,
+            (false, false, true) => LineEnding::Cr,
+            _ => LineEnding::Mixed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str) -> Vec<&str> {
+        lines(text).collect()
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(split("a\r\nb"), ["a", "b"]);
+        assert_eq!(split("a\rb\r"), ["a", "b"]);
+        assert_eq!(split("a\n\nb"), ["a", "", "b"]);
+    }
+
+    #[test]
+    fn final_line_without_break() {
+        assert_eq!(split("a\nb"), ["a", "b"]);
+        assert_eq!(split("a\nb\n"), ["a", "b"]);
+    }
+
+    #[test]
+    fn empty_text_and_single_break() {
+        assert!(split("").is_empty());
+        assert_eq!(split("\n"), [""]);
+    }
+
+    #[test]
+    fn detect_line_endings() {
+        assert_eq!(LineEnding::detect("a"), LineEnding::None);
+        assert_eq!(LineEnding::detect("a\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\rb\r"), LineEnding::Cr);
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::Mixed);
+        assert_eq!(LineEnding::detect("a\rb\n"), LineEnding::Mixed);
+    }
+}
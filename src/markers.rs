@@ -0,0 +1,36 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A marker comment such as `// TODO: ...` and where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    pub path: PathBuf,
+    /// Line number starting at 1, within its cell in notebooks
+    pub line: usize,
+    pub marker: String,
+    /// The whole line, trimmed
+    pub text: String,
+}
+
+/// Collects the marker comments of every counted file for the listing mode, as they were found
+/// by the counting pass
+#[derive(Debug, Default)]
+pub(crate) struct MarkerFinder {
+    found: Mutex<Vec<Marker>>,
+}
+
+impl MarkerFinder {
+    pub fn add(&self, path: &Path, mut found: Vec<Marker>) {
+        for marker in &mut found {
+            marker.path = path.to_owned();
+        }
+        self.found.lock().unwrap().append(&mut found);
+    }
+
+    /// All markers found so far, by file and line
+    pub fn finish(&self) -> Vec<Marker> {
+        let mut found = self.found.lock().unwrap().clone();
+        found.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+        found
+    }
+}
@@ -0,0 +1,88 @@
+use crate::codestats::CodeStats;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path};
+
+/// Where a walk is: the depth of a directory below the root, which is at depth 0, and the group
+/// its files are aggregated in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Place {
+    pub depth: usize,
+    /// The directory at the group depth the walk is in, relative to the root and `/` separated,
+    /// or a shallower one while the walk is above the group depth
+    pub group: String,
+}
+
+impl Place {
+    /// The place of a directory below this one
+    pub(crate) fn enter(&self, name: &str, group_depth: Option<usize>) -> Place {
+        let group = match group_depth {
+            Some(n) if self.depth < n => match self.group.is_empty() {
+                true => name.to_owned(),
+                false => format!("{}/{}", self.group, name),
+            },
+            _ => self.group.clone(),
+        };
+        Place {
+            depth: self.depth + 1,
+            group,
+        }
+    }
+
+    /// The place of the directory of a file found below `root`
+    pub(crate) fn of(root: &Path, path: &Path, group_depth: Option<usize>) -> Place {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        relative
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .fold(Place::default(), |place, name| {
+                place.enter(&name, group_depth)
+            })
+    }
+}
+
+/// What the stats of a file are aggregated under: its group, `.` for the root, and its language
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Key {
+    pub group: String,
+    pub lang: String,
+}
+
+impl Key {
+    pub fn new(place: &Place, lang: &str) -> Self {
+        let group = match place.group.is_empty() {
+            true => ".".to_owned(),
+            false => place.group.clone(),
+        };
+        Key {
+            group,
+            lang: lang.to_owned(),
+        }
+    }
+}
+
+/// Split stats keyed by group and language into the languages of every group
+pub fn by_group(res: &HashMap<Key, CodeStats>) -> BTreeMap<&str, HashMap<&str, CodeStats>> {
+    let mut groups: BTreeMap<&str, HashMap<&str, CodeStats>> = BTreeMap::new();
+    for (key, stats) in res {
+        *groups
+            .entry(&key.group)
+            .or_default()
+            .entry(&key.lang)
+            .or_default() += stats.clone();
+    }
+    groups
+}
+
+/// Sum stats keyed by group and language over the groups
+pub fn by_language(res: &HashMap<Key, CodeStats>) -> HashMap<&str, CodeStats> {
+    let mut langs: HashMap<&str, CodeStats> = HashMap::new();
+    for (key, stats) in res {
+        *langs.entry(&key.lang).or_default() += stats.clone();
+    }
+    langs
+}
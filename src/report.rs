@@ -0,0 +1,231 @@
+use crate::codestats::CodeStats;
+use crate::duplicates::Duplicates;
+use crate::markers::Marker;
+use crate::place::{self, Key};
+use std::collections::{BTreeSet, HashMap};
+
+/// Print the stats of every language as a table, embedded languages are listed under their parent,
+/// followed by tables of file level details, non-test and test code, complexity, line lengths,
+/// marker comments and licenses
+pub fn print_table<K: AsRef<str>>(res: &HashMap<K, CodeStats>) {
+    println!(
+        " {:<20} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "Language",
+        "Files",
+        "Lines",
+        "Logical",
+        "Codes",
+        "Structural",
+        "Comments",
+        "Docs",
+        "Blanks",
+        "Generated",
+        "Vendored",
+        "Minified"
+    );
+    for (lang, stats) in res.iter() {
+        print_row(lang.as_ref(), &stats.summarise());
+        for (child, child_stats) in stats.children.iter() {
+            print_row(&format!(" |- {}", child), child_stats);
+        }
+    }
+
+    println!();
+    println!(
+        " {:<20} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "Language", "Files", "Non-UTF8", "Binary", "Duplicates", "LF", "CRLF", "CR", "Mixed"
+    );
+    for (lang, stats) in res.iter() {
+        println!(
+            " {:<20} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            lang.as_ref(),
+            stats.files,
+            stats.non_utf8,
+            stats.binaries,
+            stats.duplicate_files,
+            stats.lf,
+            stats.crlf,
+            stats.cr,
+            stats.mixed
+        );
+    }
+
+    println!();
+    println!(
+        " {:<20} {:>12} {:>12} {:>12} {:>12}",
+        "Language", "Files", "Complexity", "Mean", "Max"
+    );
+    for (lang, stats) in res.iter() {
+        let stats = stats.summarise();
+        println!(
+            " {:<20} {:>12} {:>12} {:>12.2} {:>12}",
+            lang.as_ref(),
+            stats.files,
+            stats.complexity,
+            stats.mean_complexity(),
+            stats.max_complexity
+        );
+    }
+
+    println!();
+    println!(
+        " {:<20} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "Language", "Files", "Lines", "Codes", "Test Files", "Test Lines", "Test Codes"
+    );
+    for (lang, stats) in res.iter() {
+        let stats = stats.summarise();
+        println!(
+            " {:<20} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            lang.as_ref(),
+            stats.files - stats.test_files,
+            stats.lines() - stats.test_lines,
+            stats.codes - stats.test_codes,
+            stats.test_files,
+            stats.test_lines,
+            stats.test_codes
+        );
+    }
+
+    println!();
+    println!(
+        " {:<20} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "Language", "Bytes", "Chars", "Max Line", "Mean Line", "Long Lines"
+    );
+    for (lang, stats) in res.iter() {
+        let stats = stats.summarise();
+        println!(
+            " {:<20} {:>12} {:>12} {:>12} {:>12.2} {:>12}",
+            lang.as_ref(),
+            stats.bytes,
+            stats.chars,
+            stats.max_line_length,
+            stats.mean_line_length(),
+            stats.long_lines
+        );
+    }
+
+    let markers: BTreeSet<String> = res
+        .values()
+        .flat_map(|stats| stats.summarise().markers.into_keys())
+        .collect();
+    if !markers.is_empty() {
+        println!();
+        print!(" {:<20}", "Language");
+        markers.iter().for_each(|marker| print!(" {:>12}", marker));
+        println!();
+        for (lang, stats) in res.iter() {
+            let stats = stats.summarise();
+            print!(" {:<20}", lang.as_ref());
+            for marker in &markers {
+                print!(" {:>12}", stats.markers.get(marker).unwrap_or(&0));
+            }
+            println!();
+        }
+    }
+
+    println!();
+    println!(" {:<20} {:<32} {:>12}", "Language", "License", "Lines");
+    for (lang, stats) in res.iter() {
+        for (license, lines) in stats.licenses.iter() {
+            println!(" {:<20} {:<32} {:>12}", lang.as_ref(), license, lines);
+        }
+    }
+}
+
+/// Print the table of every group under its name, or a single table unless stats are grouped by
+/// directory
+pub fn print_groups(res: &HashMap<Key, CodeStats>) {
+    let groups = place::by_group(res);
+    if groups.keys().all(|group| *group == ".") {
+        print_table(&place::by_language(res));
+        return;
+    }
+    for (i, (group, langs)) in groups.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(" == {}", group);
+        print_table(langs);
+    }
+}
+
+/// Print every marker comment as `file:line: text`
+pub fn print_markers(markers: &[Marker]) {
+    for marker in markers {
+        println!("{}:{}: {}", marker.path.display(), marker.line, marker.text);
+    }
+}
+
+/// Print the share of duplicated code of every language, followed by the duplicated blocks and
+/// where their copies are
+pub fn print_duplicates<K: AsRef<str>>(duplicates: &Duplicates, res: &HashMap<K, CodeStats>) {
+    println!(
+        " {:<20} {:>12} {:>12} {:>12}",
+        "Language", "Codes", "Duplicated", "Percent"
+    );
+    for (lang, stats) in res.iter() {
+        // the windows of a file span its embedded blocks too
+        let codes = stats.summarise().codes;
+        let duplicated = duplicates.lines.get(lang.as_ref()).copied().unwrap_or(0);
+        let percent = duplicated_percent(duplicated, codes);
+        println!(
+            " {:<20} {:>12} {:>12} {:>12.2}",
+            lang.as_ref(),
+            codes,
+            duplicated,
+            percent
+        );
+    }
+
+    for block in &duplicates.blocks {
+        println!();
+        println!(" {} code lines duplicated in:", block.lines);
+        for location in &block.locations {
+            println!(
+                "   {}:{}-{}",
+                location.path.display(),
+                location.start,
+                location.end
+            );
+        }
+    }
+}
+
+/// The share of code lines that are duplicated. Windows are classified with the file's own
+/// syntax, which can see a few more code lines than the embedded languages do.
+fn duplicated_percent(duplicated: usize, codes: usize) -> f64 {
+    match codes {
+        0 => 0.0,
+        _ => (duplicated as f64 * 100.0 / codes as f64).min(100.0),
+    }
+}
+
+fn print_row(name: &str, stats: &CodeStats) {
+    println!(
+        " {:<20} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        name,
+        stats.files,
+        stats.lines(),
+        stats.logical,
+        stats.codes,
+        stats.structural,
+        stats.comments,
+        stats.docs,
+        stats.blanks,
+        stats.generated_lines,
+        stats.vendored_lines,
+        stats.minified_lines
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicated_percent_is_capped() {
+        assert_eq!(duplicated_percent(0, 0), 0.0);
+        assert_eq!(duplicated_percent(1, 4), 25.0);
+        assert_eq!(duplicated_percent(5, 3), 100.0);
+    }
+}
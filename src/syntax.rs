@@ -0,0 +1,887 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Comment and string rules of a language
+#[derive(Debug)]
+pub struct Syntax {
+    pub line_comments: &'static [&'static str],
+    pub block_comments: &'static [(&'static str, &'static str)],
+    /// Whether block comments can be nested, e.g. `/* /* */ */` in Rust
+    pub nested: bool,
+    /// String delimiters where a backslash escapes the next character
+    pub quotes: &'static [(&'static str, &'static str)],
+    /// String delimiters without escapes, e.g. raw strings
+    pub verbatim_quotes: &'static [(&'static str, &'static str)],
+    /// Whether `'` opens a character literal when it closes right after one character or escape,
+    /// so that lifetimes like `'a` stay code, as in Rust
+    pub char_literals: bool,
+    pub doc_comments: &'static [&'static str],
+    pub doc_block_comments: &'static [(&'static str, &'static str)],
+    /// Strings that are documentation when they open a statement, e.g. Python docstrings
+    pub doc_quotes: &'static [(&'static str, &'static str)],
+    /// Keywords and operators that open a branch, for estimating cyclomatic complexity
+    pub branches: &'static [&'static str],
+    /// What ends a statement outside of parentheses and brackets, for counting logical lines
+    pub terminators: &'static [&'static str],
+    /// Whether the end of a line ends a statement too, e.g. in Python and Go
+    pub newline_terminates: bool,
+    /// Attributes that make the next item test code, e.g. `#[cfg(test)]` in Rust
+    pub test_attributes: &'static [&'static str],
+}
+
+pub(crate) const PLAIN: Syntax = Syntax {
+    line_comments: &[],
+    block_comments: &[],
+    nested: false,
+    quotes: &[],
+    verbatim_quotes: &[],
+    char_literals: false,
+    doc_comments: &[],
+    doc_block_comments: &[],
+    doc_quotes: &[],
+    branches: &[],
+    terminators: &[],
+    newline_terminates: false,
+    test_attributes: &[],
+};
+
+pub(crate) const C_STYLE: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    quotes: &[("\"", "\""), ("'", "'")],
+    doc_comments: &["///", "//!"],
+    doc_block_comments: &[("/**", "*/"), ("/*!", "*/")],
+    branches: &["if", "for", "while", "case", "catch", "&&", "||", "?"],
+    terminators: &[";"],
+    ..PLAIN
+};
+
+pub(crate) const CPP: Syntax = Syntax {
+    verbatim_quotes: &[("R\"(", ")\"")],
+    ..C_STYLE
+};
+
+pub(crate) const JAVA: Syntax = Syntax {
+    quotes: &[("\"\"\"", "\"\"\""), ("\"", "\""), ("'", "'")],
+    ..C_STYLE
+};
+
+pub(crate) const KOTLIN: Syntax = Syntax {
+    nested: true,
+    verbatim_quotes: &[("\"\"\"", "\"\"\"")],
+    branches: &["if", "for", "while", "case", "catch", "&&", "||", "?:"],
+    newline_terminates: true,
+    ..C_STYLE
+};
+
+pub(crate) const RUST: Syntax = Syntax {
+    nested: true,
+    quotes: &[("\"", "\"")],
+    verbatim_quotes: &[("r\"", "\""), ("r#\"", "\"#"), ("r##\"", "\"##")],
+    char_literals: true,
+    branches: &["if", "for", "while", "=>", "&&", "||"],
+    test_attributes: &["#[cfg(test)]"],
+    ..C_STYLE
+};
+
+pub(crate) const SWIFT: Syntax = Syntax {
+    nested: true,
+    quotes: &[("\"\"\"", "\"\"\""), ("\"", "\"")],
+    verbatim_quotes: &[("#\"", "\"#")],
+    branches: &["if", "guard", "for", "while", "case", "catch", "&&", "||"],
+    newline_terminates: true,
+    ..C_STYLE
+};
+
+pub(crate) const GO: Syntax = Syntax {
+    verbatim_quotes: &[("`", "`")],
+    newline_terminates: true,
+    ..C_STYLE
+};
+
+pub(crate) const JAVASCRIPT: Syntax = Syntax {
+    quotes: &[("\"", "\""), ("'", "'"), ("`", "`")],
+    ..C_STYLE
+};
+
+pub(crate) const PHP: Syntax = Syntax {
+    line_comments: &["//", "#"],
+    branches: &[
+        "if", "elseif", "for", "foreach", "while", "case", "catch", "&&", "||", "?",
+    ],
+    ..C_STYLE
+};
+
+pub(crate) const CSS: Syntax = Syntax {
+    line_comments: &[],
+    doc_comments: &[],
+    doc_block_comments: &[],
+    branches: &[],
+    ..C_STYLE
+};
+
+pub(crate) const HASH: Syntax = Syntax {
+    line_comments: &["#"],
+    newline_terminates: true,
+    ..PLAIN
+};
+
+pub(crate) const SHELL: Syntax = Syntax {
+    quotes: &[("\"", "\"")],
+    verbatim_quotes: &[("'", "'")],
+    branches: &[
+        "if", "elif", "elsif", "unless", "for", "while", "until", "when", "rescue", "&&", "||",
+    ],
+    terminators: &[";"],
+    ..HASH
+};
+
+pub(crate) const PYTHON: Syntax = Syntax {
+    quotes: &[
+        ("\"\"\"", "\"\"\""),
+        ("'''", "'''"),
+        ("\"", "\""),
+        ("'", "'"),
+    ],
+    doc_quotes: &[("\"\"\"", "\"\"\""), ("'''", "'''")],
+    branches: &["if", "elif", "for", "while", "except", "case", "and", "or"],
+    terminators: &[";"],
+    ..HASH
+};
+
+pub(crate) const MARKUP: Syntax = Syntax {
+    block_comments: &[("<!--", "-->")],
+    ..PLAIN
+};
+
+pub(crate) const SQL: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("/*", "*/")],
+    quotes: &[("\"", "\""), ("'", "'")],
+    branches: &["when", "WHEN"],
+    terminators: &[";"],
+    ..PLAIN
+};
+
+pub(crate) const MATLAB: Syntax = Syntax {
+    line_comments: &["%"],
+    block_comments: &[("%{", "%}")],
+    quotes: &[("\"", "\""), ("'", "'")],
+    branches: &["if", "elseif", "for", "while", "case", "catch", "&&", "||"],
+    terminators: &[";"],
+    newline_terminates: true,
+    ..PLAIN
+};
+
+pub(crate) const PROLOG: Syntax = Syntax {
+    line_comments: &["%"],
+    block_comments: &[("/*", "*/")],
+    quotes: &[("\"", "\""), ("'", "'")],
+    branches: &[";", "->"],
+    ..PLAIN
+};
+
+pub(crate) const LUA: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("--[[", "]]")],
+    quotes: &[("\"", "\""), ("'", "'")],
+    verbatim_quotes: &[("[[", "]]")],
+    branches: &["if", "elseif", "for", "while", "repeat", "and", "or"],
+    terminators: &[";"],
+    newline_terminates: true,
+    ..PLAIN
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Blank,
+    Comment,
+    Doc,
+    Code,
+}
+
+/// Keywords that close a block, as in Ruby, Lua and shell scripts
+const STRUCTURAL_WORDS: &[&str] = &["end", "fi", "done", "esac"];
+
+/// Where the classifier is when a line ends, multi-line comments and strings carry over
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Code,
+    Comment {
+        start: &'static str,
+        end: &'static str,
+        depth: usize,
+        doc: bool,
+    },
+    Quote {
+        end: &'static str,
+        escapes: bool,
+        doc: bool,
+    },
+}
+
+/// Where the classifier is in respect of an item marked as test code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Test {
+    None,
+    /// The attribute was seen, the item has not opened its block yet
+    Pending,
+    /// Inside the item's block, by the depth of braces
+    Block(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    LineComment {
+        doc: bool,
+    },
+    BlockComment {
+        start: &'static str,
+        end: &'static str,
+        doc: bool,
+    },
+    Quote {
+        end: &'static str,
+        escapes: bool,
+        doc: bool,
+    },
+}
+
+/// A comment or string opener of a syntax and the token it starts
+#[derive(Debug)]
+struct Opener {
+    start: &'static [u8],
+    token: Token,
+    /// Doc strings only open at the start of a statement
+    statement_start: bool,
+}
+
+/// The openers of a syntax, built once per syntax and thread
+#[derive(Debug)]
+struct Openers {
+    /// Longest first, and docs first among openers of the same length
+    list: Vec<Opener>,
+    /// Bytes that some opener starts with, no other byte needs matching
+    first: [bool; 256],
+    /// Bytes the code state stops at: openers, whitespace and the quote of character literals,
+    /// runs of other bytes are copied over as code at once
+    stops: [bool; 256],
+    /// Bytes that some branch keyword or operator starts with
+    branches: [bool; 256],
+    /// Bytes that some statement terminator starts with
+    terminators: [bool; 256],
+    /// Plain block comments that close right away, like `/**/`, which are not doc openers
+    empty: Vec<Vec<u8>>,
+}
+
+impl Openers {
+    fn of(syntax: &'static Syntax) -> Arc<Openers> {
+        thread_local! {
+            static CACHE: RefCell<HashMap<usize, Arc<Openers>>> = RefCell::new(HashMap::new());
+        }
+        let key = syntax as *const Syntax as usize;
+        CACHE.with(|cache| {
+            cache
+                .borrow_mut()
+                .entry(key)
+                .or_insert_with(|| Arc::new(Openers::new(syntax)))
+                .clone()
+        })
+    }
+
+    fn new(syntax: &Syntax) -> Self {
+        let quote = |(start, end): &(&'static str, &'static str), escapes, doc| {
+            let token = Token::Quote { end, escapes, doc };
+            (*start, token, doc)
+        };
+        let line_comments = syntax
+            .line_comments
+            .iter()
+            .map(|start| (*start, Token::LineComment { doc: false }, false));
+        let block_comments = syntax.block_comments.iter().map(|(start, end)| {
+            let token = Token::BlockComment {
+                start,
+                end,
+                doc: false,
+            };
+            (*start, token, false)
+        });
+        let quotes = syntax.quotes.iter().map(|q| quote(q, true, false));
+        let verbatim_quotes = syntax
+            .verbatim_quotes
+            .iter()
+            .map(|q| quote(q, false, false));
+        let doc_comments = syntax
+            .doc_comments
+            .iter()
+            .map(|start| (*start, Token::LineComment { doc: true }, false));
+        let doc_block_comments = syntax.doc_block_comments.iter().map(|(start, end)| {
+            // nesting is driven by the plain opener, `/*` inside a `/** */` doc
+            let nest_start = syntax
+                .block_comments
+                .iter()
+                .find(|(_, e)| e == end)
+                .map_or(*start, |(s, _)| *s);
+            let token = Token::BlockComment {
+                start: nest_start,
+                end,
+                doc: true,
+            };
+            (*start, token, false)
+        });
+        let doc_quotes = syntax.doc_quotes.iter().map(|q| quote(q, true, true));
+
+        let mut list: Vec<Opener> = line_comments
+            .chain(block_comments)
+            .chain(quotes)
+            .chain(verbatim_quotes)
+            .chain(doc_comments)
+            .chain(doc_block_comments)
+            .chain(doc_quotes)
+            .filter(|(start, _, _)| !start.is_empty())
+            .map(|(start, token, statement_start)| Opener {
+                start: start.as_bytes(),
+                token,
+                statement_start,
+            })
+            .collect();
+        // the sort is stable, the later kinds in the chain are the docs
+        list.reverse();
+        list.sort_by_key(|opener| std::cmp::Reverse(opener.start.len()));
+
+        let mut first = [false; 256];
+        for opener in &list {
+            first[opener.start[0] as usize] = true;
+        }
+        let mut stops = first;
+        for b in 0..=u8::MAX {
+            stops[b as usize] |= b.is_ascii_whitespace() || (syntax.char_literals && b == b'\'');
+        }
+        let firsts = |words: &[&str]| {
+            let mut firsts = [false; 256];
+            for b in words.iter().filter_map(|w| w.as_bytes().first()) {
+                firsts[*b as usize] = true;
+            }
+            firsts
+        };
+        let empty = syntax
+            .block_comments
+            .iter()
+            .map(|(start, end)| [start.as_bytes(), end.as_bytes()].concat())
+            .collect();
+        Openers {
+            list,
+            first,
+            stops,
+            branches: firsts(syntax.branches),
+            terminators: firsts(syntax.terminators),
+            empty,
+        }
+    }
+}
+
+/// Classify the lines of a file one by one with a per-language state machine
+pub struct LineClassifier<'a> {
+    syntax: &'a Syntax,
+    openers: Arc<Openers>,
+    state: State,
+    /// The code of the last line with comments and strings blanked out
+    code: Vec<u8>,
+    /// The text of the comments of the last line
+    comment: Vec<u8>,
+    /// Open parentheses and brackets, statements inside them continue over lines
+    depth: usize,
+    /// Statements ended on the last line
+    statements: usize,
+    test: Test,
+    /// Whether the last line is part of a test item
+    in_test: bool,
+}
+
+impl<'a> LineClassifier<'a> {
+    pub fn new(syntax: &'static Syntax) -> Self {
+        LineClassifier {
+            syntax,
+            openers: Openers::of(syntax),
+            state: State::Code,
+            code: vec![],
+            comment: vec![],
+            depth: 0,
+            statements: 0,
+            test: Test::None,
+            in_test: false,
+        }
+    }
+
+    /// Any code or string makes a line code, otherwise docs win over plain comments
+    pub fn classify(&mut self, line: &str) -> LineKind {
+        self.code.clear();
+        self.comment.clear();
+        self.statements = 0;
+        self.in_test = self.test != Test::None;
+        if line.trim().is_empty() {
+            return LineKind::Blank;
+        }
+
+        let bytes = line.as_bytes();
+        let mut has_code = false;
+        let mut has_doc = false;
+        let mut has_comment = false;
+        let mut i = 0;
+        while i < bytes.len() {
+            let rest = &bytes[i..];
+            match self.state {
+                State::Comment {
+                    start,
+                    end,
+                    depth,
+                    doc,
+                } => {
+                    if doc {
+                        has_doc = true;
+                    } else {
+                        has_comment = true;
+                    }
+                    if self.syntax.nested && rest.starts_with(start.as_bytes()) {
+                        self.state = State::Comment {
+                            start,
+                            end,
+                            depth: depth + 1,
+                            doc,
+                        };
+                        i += start.len();
+                    } else if rest.starts_with(end.as_bytes()) {
+                        self.state = match depth {
+                            1 => State::Code,
+                            _ => State::Comment {
+                                start,
+                                end,
+                                depth: depth - 1,
+                                doc,
+                            },
+                        };
+                        i += end.len();
+                    } else {
+                        let stop = |b: &u8| {
+                            (self.syntax.nested && start.as_bytes().first() == Some(b))
+                                || end.as_bytes().first() == Some(b)
+                        };
+                        let run = rest[1..]
+                            .iter()
+                            .position(stop)
+                            .map_or(rest.len(), |n| n + 1);
+                        self.comment.extend_from_slice(&rest[..run]);
+                        i += run;
+                    }
+                }
+                State::Quote { end, escapes, doc } => {
+                    if doc {
+                        has_doc = true;
+                    } else {
+                        has_code = true;
+                    }
+                    if escapes && rest[0] == b'\\' {
+                        i += 2;
+                    } else if rest.starts_with(end.as_bytes()) {
+                        self.state = State::Code;
+                        i += end.len();
+                    } else {
+                        let stop =
+                            |b: &u8| (escapes && *b == b'\\') || end.as_bytes().first() == Some(b);
+                        i += rest[1..]
+                            .iter()
+                            .position(stop)
+                            .map_or(rest.len(), |n| n + 1);
+                    }
+                }
+                State::Code => {
+                    if rest[0].is_ascii_whitespace() {
+                        let run = rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+                        self.code.resize(self.code.len() + run, b' ');
+                        i += run;
+                        continue;
+                    }
+                    if let Some(len) = char_literal(rest).filter(|_| self.syntax.char_literals) {
+                        has_code = true;
+                        self.code.push(b' ');
+                        i += len;
+                        continue;
+                    }
+                    let statement_start = !has_code && !has_doc;
+                    let token = self.token_at(rest, statement_start);
+                    if token.is_some() {
+                        self.code.push(b' ');
+                        self.comment.push(b' ');
+                    }
+                    match token {
+                        Some((len, Token::LineComment { doc })) => {
+                            if doc {
+                                has_doc = true;
+                            } else {
+                                has_comment = true;
+                            }
+                            self.comment.extend_from_slice(&rest[len..]);
+                            break;
+                        }
+                        Some((len, Token::BlockComment { start, end, doc })) => {
+                            if doc {
+                                has_doc = true;
+                            } else {
+                                has_comment = true;
+                            }
+                            self.state = State::Comment {
+                                start,
+                                end,
+                                depth: 1,
+                                doc,
+                            };
+                            i += len;
+                        }
+                        Some((len, Token::Quote { end, escapes, doc })) => {
+                            if doc {
+                                has_doc = true;
+                            } else {
+                                has_code = true;
+                            }
+                            self.state = State::Quote { end, escapes, doc };
+                            i += len;
+                        }
+                        None => {
+                            let stops = &self.openers.stops;
+                            let run = rest[1..]
+                                .iter()
+                                .position(|b| stops[*b as usize])
+                                .map_or(rest.len(), |n| n + 1);
+                            has_code = true;
+                            self.code.extend_from_slice(&rest[..run]);
+                            i += run;
+                        }
+                    }
+                }
+            }
+        }
+
+        if has_code {
+            self.statements = self.count_statements();
+            self.track_tests();
+            LineKind::Code
+        } else if has_doc {
+            LineKind::Doc
+        } else if has_comment {
+            LineKind::Comment
+        } else {
+            LineKind::Blank
+        }
+    }
+
+    /// Whether the code of the last classified line is only braces, brackets and block closing
+    /// keywords, like `});` or `end`
+    pub fn is_structural(&self) -> bool {
+        let code = String::from_utf8_lossy(&self.code);
+        !code.trim().is_empty()
+            && code
+                .split(|c: char| c.is_whitespace() || "{}[]();,".contains(c))
+                .all(|word| word.is_empty() || STRUCTURAL_WORDS.contains(&word))
+    }
+
+    /// Whether the last classified line belongs to an item marked as test code
+    pub fn in_test(&self) -> bool {
+        self.in_test
+    }
+
+    /// Follow the braces of an item from its test attribute to the end of its block
+    fn track_tests(&mut self) {
+        let code = &self.code;
+        let mut start = 0;
+        for attribute in self.syntax.test_attributes {
+            if let Some(pos) = positions(code, attribute.as_bytes()).next() {
+                if self.test == Test::None {
+                    self.test = Test::Pending;
+                    start = pos + attribute.len();
+                }
+                self.in_test = true;
+            }
+        }
+        if self.test == Test::None {
+            return;
+        }
+        for b in &code[start..] {
+            self.test = match (self.test, b) {
+                (Test::Pending, b'{') => Test::Block(1),
+                (Test::Pending, b';') => Test::None,
+                (Test::Block(depth), b'{') => Test::Block(depth + 1),
+                (Test::Block(1), b'}') => Test::None,
+                (Test::Block(depth), b'}') => Test::Block(depth - 1),
+                (test, _) => test,
+            };
+        }
+    }
+
+    /// The branch keywords and operators in the code of the last classified line
+    pub fn branches(&self) -> usize {
+        let code = &self.code;
+        let starts = &self.openers.branches;
+        let mut count = 0;
+        let mut i = 0;
+        while let Some(n) = code[i..].iter().position(|b| starts[*b as usize]) {
+            i += n;
+            count += self
+                .syntax
+                .branches
+                .iter()
+                .filter(|branch| is_word_at(code, i, branch.as_bytes()))
+                .count();
+            i += 1;
+        }
+        count
+    }
+
+    /// The text of the comments of the last classified line
+    pub fn comment(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.comment)
+    }
+
+    /// How often a marker like `TODO` appears in the comments of the last classified line
+    pub fn mentions(&self, marker: &str) -> usize {
+        count_word(&self.comment, marker)
+    }
+
+    /// The logical lines ended on the last classified line
+    pub fn statements(&self) -> usize {
+        self.statements
+    }
+
+    /// Count the terminators outside of parentheses, and the line end itself in languages like
+    /// Python unless the line is continued
+    fn count_statements(&mut self) -> usize {
+        let terminators = self.syntax.terminators;
+        let starts = &self.openers.terminators;
+        let code = self.code.trim_ascii();
+        let mut depth = self.depth;
+        let mut count = 0;
+        for (i, b) in code.iter().enumerate() {
+            match b {
+                b'(' | b'[' => depth += 1,
+                b')' | b']' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if depth == 0
+                && starts[*b as usize]
+                && terminators
+                    .iter()
+                    .any(|t| code[i..].starts_with(t.as_bytes()))
+            {
+                count += 1;
+            }
+        }
+        self.depth = depth;
+        let terminated = terminators.iter().any(|t| code.ends_with(t.as_bytes()));
+        let continued = code.ends_with(b"\\");
+        if self.syntax.newline_terminates && depth == 0 && !terminated && !continued {
+            count += 1;
+        }
+        count
+    }
+
+    /// The longest comment or string opener at the head of `rest`, docs win ties
+    fn token_at(&self, rest: &[u8], statement_start: bool) -> Option<(usize, Token)> {
+        let openers = &self.openers;
+        if !openers.first[rest[0] as usize] {
+            return None;
+        }
+        openers
+            .list
+            .iter()
+            .filter(|opener| rest.starts_with(opener.start))
+            .filter(|opener| statement_start || !opener.statement_start)
+            .find(|opener| match opener.token {
+                Token::BlockComment { doc: true, .. } => {
+                    !openers.empty.iter().any(|empty| rest.starts_with(empty))
+                }
+                _ => true,
+            })
+            .map(|opener| (opener.start.len(), opener.token))
+    }
+}
+
+/// The length of a character literal like `'x'`, `'\''` or `'\u{1F600}'` at the head of `rest`,
+/// `None` for a lifetime like `'a`
+fn char_literal(rest: &[u8]) -> Option<usize> {
+    if rest.first() != Some(&b'\'') {
+        return None;
+    }
+    match *rest.get(1)? {
+        b'\'' => None,
+        b'\\' => {
+            let close = rest.get(3..)?.iter().take(10).position(|b| *b == b'\'')?;
+            Some(close + 4)
+        }
+        lead => {
+            let width = match lead {
+                0x00..=0x7f => 1,
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4,
+            };
+            (rest.get(width + 1) == Some(&b'\'')).then_some(width + 2)
+        }
+    }
+}
+
+/// Where `word` starts in `text`, only positions holding its first byte are compared further
+fn positions<'t>(text: &'t [u8], word: &'t [u8]) -> impl Iterator<Item = usize> + 't {
+    let mut from = 0;
+    std::iter::from_fn(move || {
+        let (first, tail) = word.split_first()?;
+        while let Some(n) = text.get(from..)?.iter().position(|b| b == first) {
+            let i = from + n;
+            from = i + 1;
+            if text[from..].starts_with(tail) {
+                return Some(i);
+            }
+        }
+        from = text.len() + 1;
+        None
+    })
+}
+
+/// Whether `word` is at `i` in `text`, keywords only where they aren't part of a longer word
+fn is_word_at(text: &[u8], i: usize, word: &[u8]) -> bool {
+    let is_word = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_';
+    !word.is_empty()
+        && text[i..].starts_with(word)
+        && (!word.iter().all(is_word)
+            || (!text[..i].last().is_some_and(is_word)
+                && !text.get(i + word.len()).is_some_and(is_word)))
+}
+
+/// Count the occurrences of `word` in `text`, keywords only where they aren't part of a longer word
+fn count_word(text: &[u8], word: &str) -> usize {
+    positions(text, word.as_bytes())
+        .filter(|i| is_word_at(text, *i, word.as_bytes()))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(syntax: &'static Syntax, text: &str) -> Vec<LineKind> {
+        let mut classifier = LineClassifier::new(syntax);
+        text.lines().map(|line| classifier.classify(line)).collect()
+    }
+
+    #[test]
+    fn char_literal_does_not_open_a_string() {
+        let kinds = classify(&RUST, "let q = '\"';\n// real comment\nlet s = \"x\";");
+        assert_eq!(kinds, [LineKind::Code, LineKind::Comment, LineKind::Code]);
+    }
+
+    #[test]
+    fn escaped_and_byte_char_literals() {
+        let mut classifier = LineClassifier::new(&RUST);
+        let line = "let a = '\\''; let b = b'\"'; let c = '\\u{1F600}'; // TODO";
+        assert_eq!(classifier.classify(line), LineKind::Code);
+        assert_eq!(classifier.mentions("TODO"), 1);
+        assert_eq!(classifier.statements(), 3);
+    }
+
+    #[test]
+    fn lifetimes_stay_code() {
+        let text = "fn f<'a>(x: &'a str) -> &'a str { x } // \"\n// next";
+        assert_eq!(classify(&RUST, text), [LineKind::Code, LineKind::Comment]);
+    }
+
+    #[test]
+    fn markers_in_strings_are_not_comments() {
+        let mut classifier = LineClassifier::new(&RUST);
+        classifier.classify("let s = \"TODO\"; // FIXME");
+        assert_eq!(classifier.mentions("TODO"), 0);
+        assert_eq!(classifier.mentions("FIXME"), 1);
+    }
+
+    #[test]
+    fn test_block_ends_with_its_braces() {
+        let text = "#[cfg(test)]
+mod tests {
+    fn braces() {
+        let open = '{';
+        let s = \"}}\"; // }
+    }
+}
+fn after_tests() {}";
+        let mut classifier = LineClassifier::new(&RUST);
+        let in_test: Vec<bool> = text
+            .lines()
+            .map(|line| {
+                classifier.classify(line);
+                classifier.in_test()
+            })
+            .collect();
+        assert_eq!(in_test, [true, true, true, true, true, true, true, false]);
+    }
+
+    #[test]
+    fn nested_block_comments() {
+        let kinds = classify(&RUST, "/* a /* b */\nstill */ x\n/* c */");
+        assert_eq!(
+            kinds,
+            [LineKind::Comment, LineKind::Code, LineKind::Comment]
+        );
+        let kinds = classify(&C_STYLE, "/* a /* b */\nx */");
+        assert_eq!(kinds, [LineKind::Comment, LineKind::Code]);
+    }
+
+    #[test]
+    fn doc_comments() {
+        let kinds = classify(&RUST, "/// doc\n//! inner\n// plain\n\n/** block\n */");
+        assert_eq!(
+            kinds,
+            [
+                LineKind::Doc,
+                LineKind::Doc,
+                LineKind::Comment,
+                LineKind::Blank,
+                LineKind::Doc,
+                LineKind::Doc
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_block_comment_is_not_a_doc() {
+        let kinds = classify(&C_STYLE, "int a = 1; /**/\nint b = 2;\n/**/\n/** doc */");
+        assert_eq!(
+            kinds,
+            [
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Comment,
+                LineKind::Doc
+            ]
+        );
+    }
+
+    #[test]
+    fn plain_comment_nests_inside_a_doc_block() {
+        let kinds = classify(&RUST, "/** a /* b */\nstill doc */\nx");
+        assert_eq!(kinds, [LineKind::Doc, LineKind::Doc, LineKind::Code]);
+    }
+
+    #[test]
+    fn docstrings_only_open_a_statement() {
+        let kinds = classify(&PYTHON, "x = \"\"\"text\"\"\"\n\"\"\"Doc.\"\"\"");
+        assert_eq!(kinds, [LineKind::Code, LineKind::Doc]);
+    }
+
+    #[test]
+    fn python_docstrings_and_quotes() {
+        let kinds = classify(
+            &PYTHON,
+            "def f():\n    \"\"\"Doc.\"\"\"\n    x = '# no comment'",
+        );
+        assert_eq!(kinds, [LineKind::Code, LineKind::Doc, LineKind::Code]);
+    }
+}
@@ -0,0 +1,61 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Directories that hold test code
+const DIRS: &[&str] = &["tests", "test", "androidTest", "__tests__"];
+
+/// File name suffixes of test code
+const SUFFIXES: &[&str] = &[
+    "_test.go",
+    "_test.py",
+    "_test.rs",
+    "_test.cc",
+    "_test.cpp",
+    "_unittest.cc",
+    "Test.java",
+    "Tests.java",
+    "Test.kt",
+    "Tests.kt",
+    "Tests.swift",
+    ".test.js",
+    ".test.ts",
+    ".spec.js",
+    ".spec.ts",
+];
+
+/// Whether a file is test code by its directory or name, given its path below the walk root
+pub(crate) fn is_test_path(path: &Path) -> bool {
+    let in_dir = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .any(|c| DIRS.iter().any(|dir| c.as_os_str() == *dir));
+    let name = path.file_name().and_then(OsStr::to_str).unwrap_or("");
+    in_dir
+        || SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+        || (name.starts_with("test_") && name.ends_with(".py"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{Counter, Options};
+    use crate::language::Language;
+
+    #[test]
+    fn test_names_and_dirs() {
+        assert!(is_test_path(Path::new("tests/cli.rs")));
+        assert!(is_test_path(Path::new("src/parser_test.go")));
+        assert!(is_test_path(Path::new("test_parser.py")));
+        assert!(!is_test_path(Path::new("src/testing.rs")));
+    }
+
+    #[test]
+    fn tests_dir_above_the_root_is_not_test_code() {
+        let counter = Counter::new(Options::default(), &[Path::new("/work/tests/tool")]);
+        let rust = Language::from_name("rust").unwrap();
+        let count = |path: &str| counter.count(Path::new(path), b"fn main() {}\n", rust);
+        assert_eq!(count("/work/tests/tool/src/main.rs").test_files, 0);
+        assert_eq!(count("/work/tests/tool/tests/cli.rs").test_files, 1);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::counter::Options;
+use crate::lines::lines;
+use glob::{MatchOptions, Pattern};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Directories of third-party code checked into a repository
+const DIRS: &[&str] = &[
+    "third_party",
+    "third-party",
+    "thirdparty",
+    "external",
+    "vendor",
+    "node_modules",
+    "bower_components",
+];
+
+/// Path patterns of minified bundles
+const MINIFIED: &[&str] = &["*.min.js", "*.min.css", "*-min.js", "*.bundle.js"];
+
+/// Any line longer than this is a candidate for minified content
+const LONG_LINE: usize = 1000;
+
+/// Minified lines hold less than one whitespace in this many characters
+const WHITESPACE_RATIO: usize = 20;
+
+/// Files whose lines are this long on average are minified whatever their whitespace
+const AVERAGE_LINE: usize = 250;
+
+/// A `linguist-vendored` rule of a `.gitattributes` file
+#[derive(Debug)]
+struct Attribute {
+    pattern: Pattern,
+    /// Whether the pattern is matched against the path relative to the attributes file instead of
+    /// the file name
+    anchored: bool,
+    vendored: bool,
+}
+
+/// Tells third-party and minified files apart from the project's own code
+#[derive(Debug)]
+pub(crate) struct Vendored {
+    paths: Vec<Pattern>,
+    minified: Vec<Pattern>,
+    /// Parsed `.gitattributes` of every directory seen so far
+    attributes: Mutex<HashMap<PathBuf, Arc<Vec<Attribute>>>>,
+}
+
+impl Vendored {
+    pub fn new(options: &Options) -> Self {
+        Vendored {
+            paths: options.vendored_paths.clone(),
+            minified: MINIFIED.iter().map(|p| Pattern::new(p).unwrap()).collect(),
+            attributes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Vendored by a well-known directory or an extra pattern, which are matched against the
+    /// path below the walk root, or by `linguist-vendored`
+    pub fn is_vendored(&self, path: &Path, relative: &Path) -> bool {
+        let in_dir = relative
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .any(|c| DIRS.iter().any(|dir| c.as_os_str() == *dir));
+        in_dir
+            || self.paths.iter().any(|p| p.matches_path(relative))
+            || self.linguist_vendored(path)
+    }
+
+    /// Minified by name, or by long lines that are almost free of whitespace
+    pub fn is_minified(&self, path: &Path, content: &str) -> bool {
+        if self.minified.iter().any(|p| p.matches_path(path)) {
+            return true;
+        }
+        let (mut count, mut total) = (0, 0);
+        for line in lines(content) {
+            let len = line.chars().count();
+            if len > LONG_LINE {
+                let spaces = line.chars().filter(|c| c.is_whitespace()).count();
+                if spaces * WHITESPACE_RATIO < len {
+                    return true;
+                }
+            }
+            count += 1;
+            total += len;
+        }
+        count > 0 && total / count > AVERAGE_LINE
+    }
+
+    /// Look up the `.gitattributes` of every directory up to the repository root, the deepest
+    /// file and the last matching line win
+    fn linguist_vendored(&self, path: &Path) -> bool {
+        for dir in path.ancestors().skip(1) {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            let relative = path.strip_prefix(dir).unwrap_or(path);
+            let attributes = self.attributes(dir);
+            let found = attributes.iter().rev().find(|attr| {
+                let options = MatchOptions {
+                    require_literal_separator: true,
+                    ..Default::default()
+                };
+                match (attr.anchored, relative.file_name()) {
+                    (true, _) => attr.pattern.matches_path_with(relative, options),
+                    (false, Some(name)) => attr.pattern.matches(&name.to_string_lossy()),
+                    (false, None) => false,
+                }
+            });
+            if let Some(attr) = found {
+                return attr.vendored;
+            }
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        false
+    }
+
+    fn attributes(&self, dir: &Path) -> Arc<Vec<Attribute>> {
+        let mut cache = self.attributes.lock().unwrap();
+        cache
+            .entry(dir.to_owned())
+            .or_insert_with(|| Arc::new(read_attributes(dir)))
+            .clone()
+    }
+}
+
+/// The `linguist-vendored` rules of a directory's `.gitattributes`, if it has one
+fn read_attributes(dir: &Path) -> Vec<Attribute> {
+    let content = std::fs::read_to_string(dir.join(".gitattributes")).unwrap_or_default();
+    lines(&content)
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let pattern = words.next()?;
+            let vendored = words.find_map(|attr| match attr {
+                "linguist-vendored" | "linguist-vendored=true" => Some(true),
+                "-linguist-vendored" | "!linguist-vendored" | "linguist-vendored=false" => {
+                    Some(false)
+                }
+                _ => None,
+            })?;
+            let anchored = pattern.trim_end_matches('/').contains('/');
+            let pattern = Pattern::new(pattern.trim_start_matches('/')).ok()?;
+            Some(Attribute {
+                pattern,
+                anchored,
+                vendored,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::counter::{Counter, Options};
+    use crate::language::Language;
+    use std::path::Path;
+
+    #[test]
+    fn vendored_dir_above_the_root_does_not_vendor_the_walk() {
+        let counter = Counter::new(Options::default(), &[Path::new("/work/third_party/lib")]);
+        let rust = Language::from_name("rust").unwrap();
+        let count = |path: &str| counter.count(Path::new(path), b"fn main() {}\n", rust);
+        let own = count("/work/third_party/lib/src/main.rs");
+        assert_eq!((own.files, own.vendored_files), (1, 0));
+        let vendored = count("/work/third_party/lib/vendor/dep.rs");
+        assert_eq!((vendored.files, vendored.vendored_files), (0, 1));
+    }
+}